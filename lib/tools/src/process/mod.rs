@@ -174,7 +174,7 @@ where
     }
 }
 
-fn into_pointer(p: &str) -> String {
+pub fn into_pointer(p: &str) -> String {
     let mut p = if p.find('/').is_none() {
         p.replace('.', "/")
     } else {
@@ -46,7 +46,7 @@ pub enum Command {
     SetOutputMode(OutputMode),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum OutputMode {
     Json,
     Yaml,
@@ -1,6 +1,6 @@
 use std::path::{Path, PathBuf};
 use std::fs::File;
-use std::io::{stdin, BufRead, BufReader, Read, Write};
+use std::io::{stdin, Read, Write};
 use serde_yaml;
 use util::{strip_ext, write_at, FingerprintUserId, ResetCWD};
 use error::{IOMode, VaultError};
@@ -13,6 +13,10 @@ use std::iter::once;
 use std::fs::create_dir_all;
 use std::str::FromStr;
 use std::io;
+use metadata::{is_metadata_path, Metadata};
+use tags::TagIndex;
+use recipients::{Groups, KeyPolicy, RecipientsFile};
+use std::collections::BTreeMap;
 
 pub const GPG_GLOB: &str = "**/*.gpg";
 pub fn recipients_default() -> PathBuf {
@@ -40,6 +44,7 @@ impl Default for VaultKind {
 pub enum TrustModel {
     GpgWebOfTrust,
     Always,
+    VaultMembers,
 }
 
 impl Default for TrustModel {
@@ -55,6 +60,7 @@ impl FromStr for TrustModel {
         Ok(match s {
             "web-of-trust" => TrustModel::GpgWebOfTrust,
             "always" => TrustModel::Always,
+            "vault-members" => TrustModel::VaultMembers,
             _ => return Err(format!("Unknown trust model: '{}'", s)),
         })
     }
@@ -82,6 +88,22 @@ pub struct Vault {
     pub gpg_keys: Option<PathBuf>,
     #[serde(default = "recipients_default")]
     pub recipients: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub templates: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_versions: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypt_metadata: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_certifications: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_policy: Option<KeyPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed_recipients: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Groups>,
+    #[serde(skip)]
+    pub shared_groups: Groups,
 }
 
 impl Default for Vault {
@@ -98,6 +120,14 @@ impl Default for Vault {
             resolved_at: secrets_default(),
             gpg_keys: None,
             recipients: recipients_default(),
+            templates: None,
+            keep_versions: None,
+            encrypt_metadata: None,
+            required_certifications: None,
+            key_policy: None,
+            signed_recipients: None,
+            groups: None,
+            shared_groups: Groups::new(),
         }
     }
 }
@@ -123,6 +153,14 @@ impl Vault {
                             recipients: recipients_default(),
                             auto_import: Some(false),
                             trust_model: Some(TrustModel::GpgWebOfTrust),
+                            templates: None,
+                            keep_versions: None,
+                            encrypt_metadata: None,
+                            required_certifications: None,
+                            key_policy: None,
+                            signed_recipients: None,
+                            groups: None,
+                            shared_groups: Groups::new(),
                         };
                         vault = vault.set_resolved_at(&recipients_path
                             .parent()
@@ -263,27 +301,84 @@ impl Vault {
         )
     }
 
-    pub fn print_resources(&self, w: &mut Write) -> Result<(), Error> {
+    /// All resources of this vault, without partitions, relative to its secrets directory and without extension.
+    pub fn resources(&self) -> Result<Vec<PathBuf>, Error> {
+        let dir = self.secrets_path();
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let _change_cwd = ResetCWD::new(&dir)?;
+        Ok(glob(GPG_GLOB)
+            .expect("valid pattern")
+            .filter_map(Result::ok)
+            .filter(|p| !is_metadata_path(p))
+            .map(|p| strip_ext(&p))
+            .collect())
+    }
+
+    /// Print all resources whose metadata has all fields given as `(name, value)` pairs in `filters`
+    /// and which carry all `tags`, optionally followed by their metadata.
+    pub fn print_resources(
+        &self,
+        filters: &[(String, String)],
+        tags: &[String],
+        show_metadata: bool,
+        w: &mut Write,
+    ) -> Result<(), Error> {
         let has_multiple_partitions = !self.partitions.is_empty();
         for partition in once(self).chain(self.partitions.iter()) {
             writeln!(w, "{}", partition.url())?;
             let dir = partition.secrets_path();
-            if !dir.is_dir() {
-                continue;
-            }
-            let _change_cwd = ResetCWD::new(&dir)?;
-            for entry in glob(GPG_GLOB).expect("valid pattern").filter_map(Result::ok) {
+            let tag_index = if tags.is_empty() {
+                TagIndex::new()
+            } else {
+                partition.read_tags()?
+            };
+            for resource in partition.resources()? {
+                if !tags.is_empty() {
+                    let resource_tags = tag_index.get(&*resource.to_string_lossy());
+                    if !tags.iter().all(|t| resource_tags.map_or(false, |rt| rt.contains(t))) {
+                        continue;
+                    }
+                }
+                let metadata = if show_metadata || !filters.is_empty() {
+                    self.read_metadata(partition, &resource)?.unwrap_or_default()
+                } else {
+                    Metadata::new()
+                };
+                if !filters
+                    .iter()
+                    .all(|&(ref name, ref value)| metadata.get(name) == Some(value))
+                {
+                    continue;
+                }
                 if has_multiple_partitions {
-                    writeln!(w, "{}", dir.join(strip_ext(&entry)).display())?;
+                    write!(w, "{}", dir.join(&resource).display())?;
                 } else {
-                    writeln!(w, "{}", strip_ext(&entry).display())?;
+                    write!(w, "{}", resource.display())?;
                 }
+                if show_metadata {
+                    for (name, value) in &metadata {
+                        write!(w, "\t{}={}", name, value)?;
+                    }
+                }
+                writeln!(w)?;
             }
         }
         Ok(())
     }
 
     pub fn write_recipients_list(&self, recipients: &mut Vec<String>) -> Result<PathBuf, Error> {
+        self.write_recipients_file(recipients, &BTreeMap::new())
+    }
+
+    /// Write `recipients` sorted, keeping the comments of existing recipients and using the ones
+    /// in `comments` instead, if present.
+    pub fn write_recipients_file(
+        &self,
+        recipients: &mut Vec<String>,
+        comments: &BTreeMap<String, String>,
+    ) -> Result<PathBuf, Error> {
         recipients.sort();
         recipients.dedup();
 
@@ -296,17 +391,18 @@ impl Vault {
                 ))?;
             }
         }
-        let mut writer = write_at(&recipients_path).context(format!(
-            "Failed to open recipients at '{}' file for writing",
-            recipients_path.display()
-        ))?;
-        for recipient in recipients {
-            writeln!(&mut writer, "{}", recipient).context(format!(
-                "Failed to write recipient '{}' to file at '{}'",
-                recipient,
+        let mut file = if recipients_path.is_file() {
+            self.read_recipients_file()?
+        } else {
+            RecipientsFile::default()
+        };
+        file.set_ids(recipients, comments);
+        write_at(&recipients_path)
+            .and_then(|mut w| w.write_all(file.render().as_bytes()))
+            .context(format!(
+                "Failed to write recipients to file at '{}'",
                 recipients_path.display()
-            ))?
-        }
+            ))?;
         Ok(recipients_path)
     }
 
@@ -314,16 +410,28 @@ impl Vault {
         self.absolute_path(&self.recipients)
     }
 
-    pub fn recipients_list(&self) -> Result<Vec<String>, Error> {
+    fn read_recipients_file(&self) -> Result<RecipientsFile, Error> {
         let recipients_file_path = self.recipients_path();
-        let rfile = File::open(&recipients_file_path).map(BufReader::new).context(format!(
+        let mut rfile = File::open(&recipients_file_path).context(format!(
             "Could not open recipients file at '{}' for reading",
             recipients_file_path.display()
         ))?;
-        Ok(rfile.lines().collect::<Result<_, _>>().context(format!(
+        let mut content = String::new();
+        rfile.read_to_string(&mut content).context(format!(
             "Could not read all recipients from file at '{}'",
             recipients_file_path.display()
-        ))?)
+        ))?;
+        Ok(RecipientsFile::parse(&content))
+    }
+
+    /// The entries of the recipients file, which are fingerprints or references to groups.
+    pub fn recipients_entries(&self) -> Result<Vec<String>, Error> {
+        Ok(self.read_recipients_file()?.ids())
+    }
+
+    /// The comment on the line of the recipients file with the entry `id`, if there is one.
+    pub fn recipient_comment(&self, id: &str) -> Result<Option<String>, Error> {
+        Ok(self.read_recipients_file()?.comment_of(id).map(ToOwned::to_owned))
     }
 
     pub fn keys_by_ids(
@@ -425,10 +533,11 @@ impl Vault {
                 self.recipients.display()
             ));
         }
+        self.verify_recipients_signature(ctx)?;
         self.keys_by_ids(ctx, &recipients_fprs, "recipient", gpg_keys_dir, output)
     }
 
-    fn vault_path_for_display(&self) -> String {
+    pub fn vault_path_for_display(&self) -> String {
         self.vault_path
             .as_ref()
             .map(|p| p.to_string_lossy().into_owned())
@@ -493,6 +602,7 @@ impl VaultExt for Vec<Vault> {
         });
 
         vault.partitions = self;
+        vault.share_groups();
         Ok(vault)
     }
 }
@@ -0,0 +1,274 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use base::Vault;
+use failure::{Error, ResultExt};
+use serde_json as json;
+use serde_yaml as yaml;
+use spec::{Destination, SpecSourceType, VaultSpec, WriteMode};
+use tools::process::into_pointer;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DocumentFormat {
+    Json,
+    Yaml,
+}
+
+/// A structured resource, which remembers the format it was read in to be able to write itself back the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub format: DocumentFormat,
+    pub value: yaml::Value,
+    has_document_marker: bool,
+}
+
+fn tokens_of(pointer: &str) -> Vec<String> {
+    into_pointer(pointer)
+        .split('/')
+        .skip(1)
+        .map(|t| t.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+fn key_of(token: &str) -> yaml::Value {
+    yaml::Value::String(token.to_owned())
+}
+
+/// The pointer and value of an assignment. `pointer=value` stores `value` as string, while `pointer:=value`
+/// reads it as JSON to store numbers, booleans, sequences or mappings.
+fn assignment_of<'a>(pointer: &'a str, text: &str) -> Result<(&'a str, yaml::Value), Error> {
+    if pointer.ends_with(':') {
+        json::from_str::<json::Value>(text).context(format!(
+            "The value of '{}' must be valid JSON, like 5432, true or \"text\", got '{}'.",
+            pointer, text
+        ))?;
+        // JSON is valid YAML, which keeps integers as such
+        Ok((
+            &pointer[..pointer.len() - 1],
+            yaml::from_str(text).context("Failed to read JSON value.")?,
+        ))
+    } else {
+        Ok((pointer, yaml::Value::String(text.to_owned())))
+    }
+}
+
+impl Document {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let format = if json::from_slice::<json::Value>(bytes).is_ok() {
+            DocumentFormat::Json
+        } else {
+            DocumentFormat::Yaml
+        };
+        let value = if bytes.iter().all(|b| b.is_ascii_whitespace()) {
+            yaml::Value::Mapping(yaml::Mapping::new())
+        } else {
+            yaml::from_slice(bytes).context("The resource does not contain a valid JSON or YAML document.")?
+        };
+        Ok(Document {
+            format,
+            value,
+            has_document_marker: bytes.starts_with(b"---"),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(match self.format {
+            DocumentFormat::Json => {
+                let mut buf = json::to_vec_pretty(&self.value).context("Failed to serialize document as JSON.")?;
+                buf.push(b'\n');
+                buf
+            }
+            DocumentFormat::Yaml => {
+                let mut s = yaml::to_string(&self.value).context("Failed to serialize document as YAML.")?;
+                if !self.has_document_marker && s.starts_with("---\n") {
+                    s = s.split_off(4);
+                }
+                s.push('\n');
+                s.into_bytes()
+            }
+        })
+    }
+
+    pub fn get(&self, pointer: &str) -> Result<&yaml::Value, Error> {
+        let mut current = &self.value;
+        for token in tokens_of(pointer) {
+            current = match *current {
+                yaml::Value::Mapping(ref m) => m.get(&key_of(&token)),
+                yaml::Value::Sequence(ref s) => token.parse::<usize>().ok().and_then(|i| s.get(i)),
+                _ => None,
+            }.ok_or_else(|| format_err!("There is no value at '{}'", pointer))?;
+        }
+        Ok(current)
+    }
+
+    /// Set `value` at `pointer`, creating intermediate mappings as needed.
+    pub fn set(&mut self, pointer: &str, value: yaml::Value) -> Result<(), Error> {
+        let mut current = &mut self.value;
+        for token in tokens_of(pointer) {
+            if let yaml::Value::Null = *current {
+                *current = yaml::Value::Mapping(yaml::Mapping::new());
+            }
+            current = match *current {
+                yaml::Value::Mapping(ref mut m) => {
+                    let key = key_of(&token);
+                    if !m.contains_key(&key) {
+                        m.insert(key.clone(), yaml::Value::Null);
+                    }
+                    m.get_mut(&key).expect("value just inserted")
+                }
+                yaml::Value::Sequence(ref mut s) => {
+                    let len = s.len();
+                    match token.parse::<usize>() {
+                        Ok(index) if index < len => &mut s[index],
+                        Ok(index) if index == len => {
+                            s.push(yaml::Value::Null);
+                            &mut s[index]
+                        }
+                        _ => bail!(
+                            "Cannot set '{}' as '{}' is not a valid index into a list of {} items",
+                            pointer,
+                            token,
+                            len
+                        ),
+                    }
+                }
+                _ => bail!(
+                    "Cannot set '{}' as '{}' does not refer to a mapping or list",
+                    pointer,
+                    token
+                ),
+            };
+        }
+        *current = value;
+        Ok(())
+    }
+
+    pub fn write_value(&self, value: &yaml::Value, w: &mut Write) -> Result<(), Error> {
+        match *value {
+            yaml::Value::Null => {}
+            yaml::Value::Bool(ref v) => writeln!(w, "{}", v)?,
+            yaml::Value::Number(ref v) => writeln!(w, "{}", v)?,
+            yaml::Value::String(ref v) => writeln!(w, "{}", v)?,
+            yaml::Value::Sequence(_) | yaml::Value::Mapping(_) => {
+                let doc = Document {
+                    format: self.format,
+                    value: value.clone(),
+                    has_document_marker: false,
+                };
+                w.write_all(&doc.to_bytes()?)?
+            }
+        };
+        Ok(())
+    }
+}
+
+impl Vault {
+    pub fn decrypt_document(&self, path: &Path) -> Result<(Document, PathBuf), Error> {
+        let mut buf = Vec::new();
+        let decrypted_path = self.decrypt(path, &mut buf)?;
+        let doc = Document::from_bytes(&buf).context(format!(
+            "Could not read structured data from resource at '{}'.",
+            path.display()
+        ))?;
+        Ok((doc, decrypted_path))
+    }
+
+    pub fn show_pointers(&self, path: &Path, pointers: &[String], output: &mut Write) -> Result<(), Error> {
+        let (doc, _) = self.decrypt_document(path)?;
+        for pointer in pointers {
+            doc.write_value(doc.get(pointer)?, output)?;
+        }
+        Ok(())
+    }
+
+    /// Set the values given as `(pointer, value)` pairs in `assignments`.
+    /// Warnings about the keys of recipients are written to `error`.
+    pub fn set_pointers(
+        &self,
+        path: &Path,
+        assignments: &[(String, String)],
+        output: &mut Write,
+        error: &mut Write,
+    ) -> Result<(), Error> {
+        let (mut doc, decrypted_path) = self.decrypt_document(path)?;
+        let original = doc.clone();
+        for &(ref pointer, ref text) in assignments {
+            let (pointer, value) = assignment_of(pointer, text)?;
+            doc.set(pointer, value)?;
+        }
+        if doc == original {
+            writeln!(output, "No changes to '{}'.", path.display()).ok();
+            return Ok(());
+        }
+        self.encrypt(
+            &[
+                VaultSpec {
+                    src: SpecSourceType::Buffer(doc.to_bytes()?),
+                    dst: decrypted_path,
+                },
+            ],
+            WriteMode::AllowOverwrite,
+            Destination::Unchanged,
+            &mut io::sink(),
+            error,
+        ).context("Failed to re-encrypt updated content.")?;
+        writeln!(output, "Updated '{}'.", path.display()).ok();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_document {
+    use super::*;
+
+    #[test]
+    fn it_gets_values_using_dotted_and_slashed_pointers() {
+        let doc = Document::from_bytes(b"a:\n  b: secret\n  c:\n    - first\n").unwrap();
+        assert_eq!(doc.get("a.b").unwrap(), &yaml::Value::String("secret".into()));
+        assert_eq!(doc.get("/a/c/0").unwrap(), &yaml::Value::String("first".into()));
+        assert_eq!(
+            format!("{}", doc.get("a.d").unwrap_err()),
+            "There is no value at 'a.d'"
+        );
+    }
+
+    #[test]
+    fn it_sets_values_and_creates_intermediate_mappings() {
+        let mut doc = Document::from_bytes(b"a:\n  b: old\nz: 1\n").unwrap();
+        doc.set("a.b", yaml::Value::String("new".into())).unwrap();
+        doc.set("db.user", yaml::Value::String("inserted".into())).unwrap();
+        assert_eq!(
+            String::from_utf8(doc.to_bytes().unwrap()).unwrap(),
+            "a:\n  b: new\nz: 1\ndb:\n  user: inserted\n"
+        );
+    }
+
+    #[test]
+    fn it_preserves_the_json_format_and_key_order() {
+        let mut doc = Document::from_bytes(b"{\"z\": \"1\", \"a\": \"2\"}").unwrap();
+        assert_eq!(doc.format, DocumentFormat::Json);
+        doc.set("a", yaml::Value::String("3".into())).unwrap();
+        assert_eq!(
+            String::from_utf8(doc.to_bytes().unwrap()).unwrap(),
+            "{\n  \"z\": \"1\",\n  \"a\": \"3\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn it_stores_strings_unless_a_json_value_is_assigned() {
+        let string = |s: &str| yaml::Value::String(s.into());
+        assert_eq!(assignment_of("pin", "0123").unwrap(), ("pin", string("0123")));
+        assert_eq!(assignment_of("flag", "yes").unwrap(), ("flag", string("yes")));
+        assert_eq!(assignment_of("a.b", "").unwrap(), ("a.b", string("")));
+        assert_eq!(assignment_of("port:", "5432").unwrap(), ("port", yaml::Value::from(5432)));
+        assert_eq!(assignment_of("flag:", "true").unwrap(), ("flag", yaml::Value::Bool(true)));
+        assert_eq!(assignment_of("name:", "\"0123\"").unwrap(), ("name", string("0123")));
+        assert!(assignment_of("pin:", "0123").is_err());
+    }
+
+    #[test]
+    fn it_refuses_to_set_values_below_scalars() {
+        let mut doc = Document::from_bytes(b"a: scalar").unwrap();
+        assert!(doc.set("a.b", yaml::Value::Null).is_err());
+    }
+}
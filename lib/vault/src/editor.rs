@@ -0,0 +1,175 @@
+use std::env;
+use std::io::{stdin, stderr, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use failure::{Error, ResultExt};
+use tempfile::EditorSession;
+
+/// If set to a non-empty value, we will wait for confirmation after the editor exited.
+const WAIT_ENV_VAR: &str = "SY_EDITOR_WAIT";
+
+/// An editor command line, like `vim` or `code --wait`, which gets the file to edit appended.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Editor {
+    pub command: String,
+    /// Wait for confirmation after the editor exited, for editors which return before the file was closed.
+    pub wait: bool,
+}
+
+impl Editor {
+    /// The editor command configured by `$VISUAL` or `$EDITOR`, ignoring empty values.
+    pub fn command_from_env() -> Option<String> {
+        first_non_empty(vec![env::var("VISUAL").ok(), env::var("EDITOR").ok()])
+    }
+
+    /// The editor configured by `$VISUAL` or `$EDITOR`, falling back to `vim`.
+    pub fn from_env() -> Self {
+        Editor {
+            command: default_command(),
+            wait: wait_from_env(),
+        }
+    }
+
+    pub fn run(&self, path_to_edit: &Path) -> Result<(), Error> {
+        self.run_all(&[path_to_edit])
+    }
+
+    /// Open all `paths_to_edit` in a single editor session.
+    pub fn run_all<P: AsRef<Path>>(&self, paths_to_edit: &[P]) -> Result<(), Error> {
+        let words = split_shell_words(&self.command)?;
+        let (program, args) = words
+            .split_first()
+            .ok_or_else(|| format_err!("The editor command line is empty."))?;
+        let status = {
+            let _session = EditorSession::new();
+            let mut running_program = Command::new(program)
+                .args(args)
+                .args(paths_to_edit.iter().map(AsRef::as_ref))
+                .stdin(Stdio::inherit())
+                .stdout(Stdio::inherit())
+                .stderr(Stdio::inherit())
+                .spawn()
+                .with_context(|_| format!("Failed to start editor program at '{}'.", program))?;
+            running_program
+                .wait()
+                .with_context(|_| "Failed to wait for editor to exit.")?
+        };
+        if !status.success() {
+            return Err(format_err!("Editor '{}' failed. Edit aborted.", self.command));
+        }
+        if self.wait {
+            write!(stderr(), "Press Enter once you are done editing.").ok();
+            stdin()
+                .read_line(&mut String::new())
+                .context("Failed to wait for confirmation that editing is done.")?;
+        }
+        Ok(())
+    }
+}
+
+fn default_command() -> String {
+    Editor::command_from_env().unwrap_or_else(|| "vim".into())
+}
+
+/// The first of `candidates` which isn't empty or whitespace only.
+fn first_non_empty<I: IntoIterator<Item = Option<String>>>(candidates: I) -> Option<String> {
+    candidates.into_iter().flatten().find(|c| !c.trim().is_empty())
+}
+
+fn wait_from_env() -> bool {
+    env::var_os(WAIT_ENV_VAR).map_or(false, |v| !v.is_empty())
+}
+
+/// Split `input` into words like a POSIX shell would, supporting quotes and backslash escapes.
+fn split_shell_words(input: &str) -> Result<Vec<String>, Error> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(w) = word.take() {
+                    words.push(w);
+                }
+            }
+            '\'' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => w.push(c),
+                        None => bail!("Missing closing single quote in '{}'", input),
+                    }
+                }
+            }
+            '"' => {
+                let w = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) if "\"\\$`".contains(c) => w.push(c),
+                            Some(c) => {
+                                w.push('\\');
+                                w.push(c)
+                            }
+                            None => bail!("Missing closing double quote in '{}'", input),
+                        },
+                        Some(c) => w.push(c),
+                        None => bail!("Missing closing double quote in '{}'", input),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => bail!("Trailing backslash in '{}'", input),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests_first_non_empty {
+    use super::*;
+
+    #[test]
+    fn it_skips_unset_and_empty_values() {
+        assert_eq!(
+            first_non_empty(vec![Some(" ".into()), None, Some("nano".into())]),
+            Some("nano".into())
+        );
+        assert_eq!(first_non_empty(vec![Some("".into()), None]), None);
+        assert_eq!(
+            first_non_empty(vec![Some("code --wait".into()), Some("vim".into())]),
+            Some("code --wait".into())
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_split_shell_words {
+    use super::*;
+
+    #[test]
+    fn it_splits_at_whitespace() {
+        assert_eq!(split_shell_words("  code   --wait ").unwrap(), vec!["code", "--wait"]);
+    }
+
+    #[test]
+    fn it_handles_quotes_and_escapes() {
+        assert_eq!(
+            split_shell_words(r#"'/Applications/Sublime Text/subl' -w "a \"b\"" c\ d ''"#).unwrap(),
+            vec!["/Applications/Sublime Text/subl", "-w", "a \"b\"", "c d", ""]
+        );
+    }
+
+    #[test]
+    fn it_fails_on_unterminated_quotes() {
+        assert!(split_shell_words("vim 'foo").is_err());
+        assert!(split_shell_words("vim \"foo").is_err());
+    }
+}
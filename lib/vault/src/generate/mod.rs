@@ -0,0 +1,337 @@
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use base64;
+use base::Vault;
+use failure::{Error, ResultExt};
+use rand::{OsRng, Rng};
+use spec::{Destination, SpecSourceType, VaultSpec, WriteMode};
+
+/// The english BIP-39 word list, 2048 words which are easy to type and distinguish.
+const WORDLIST: &str = include_str!("wordlist.txt");
+const AMBIGUOUS_CHARACTERS: &str = "0O1lI|";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum CharacterClass {
+    Lower,
+    Upper,
+    Digit,
+    Symbol,
+    Alpha,
+    Alnum,
+    Hex,
+}
+
+impl CharacterClass {
+    fn characters(&self) -> &'static str {
+        use self::CharacterClass::*;
+        match *self {
+            Lower => "abcdefghijklmnopqrstuvwxyz",
+            Upper => "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            Digit => "0123456789",
+            Symbol => "!#$%&()*+,-./:;<=>?@[]^_{}~",
+            Alpha => "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            Alnum => "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789",
+            Hex => "0123456789abcdef",
+        }
+    }
+}
+
+impl FromStr for CharacterClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, <Self as FromStr>::Err> {
+        use self::CharacterClass::*;
+        Ok(match s {
+            "lower" => Lower,
+            "upper" => Upper,
+            "digit" => Digit,
+            "symbol" => Symbol,
+            "alpha" => Alpha,
+            "alnum" => Alnum,
+            "hex" => Hex,
+            _ => return Err(format!("Unknown character class: '{}'", s)),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Generator {
+    Password {
+        length: usize,
+        classes: Vec<CharacterClass>,
+        exclude_ambiguous: bool,
+    },
+    Passphrase {
+        words: usize,
+        separator: String,
+    },
+    Hex {
+        bytes: usize,
+    },
+    Base64 {
+        bytes: usize,
+    },
+    Template {
+        template: String,
+        exclude_ambiguous: bool,
+    },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum TemplatePart {
+    Literal(String),
+    Random { length: usize, class: CharacterClass },
+}
+
+fn parse_template(template: &str) -> Result<Vec<TemplatePart>, Error> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            parts.push(TemplatePart::Literal(rest[..start].to_owned()));
+        }
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| format_err!("Unterminated placeholder in template '{}'", template))?;
+        let placeholder = &rest[start + 2..start + end];
+        let mut tokens = placeholder.splitn(2, ':');
+        parts.push(match (tokens.next(), tokens.next()) {
+            (Some(length), Some(class)) => TemplatePart::Random {
+                length: length.trim().parse().map_err(|_| {
+                    format_err!(
+                        "Placeholder '{{{{{}}}}}' must start with the amount of characters to generate",
+                        placeholder
+                    )
+                })?,
+                class: class.trim().parse().map_err(|e: String| format_err!("{}", e))?,
+            },
+            _ => bail!(
+                "Placeholder '{{{{{}}}}}' must have the form '{{{{<length>:<class>}}}}'",
+                placeholder
+            ),
+        });
+        rest = &rest[start + end + 2..];
+    }
+    if !rest.is_empty() {
+        parts.push(TemplatePart::Literal(rest.to_owned()));
+    }
+    Ok(parts)
+}
+
+fn alphabet_of(classes: &[CharacterClass], exclude_ambiguous: bool) -> Vec<char> {
+    let mut chars: Vec<char> = classes
+        .iter()
+        .flat_map(|c| c.characters().chars())
+        .filter(|c| !exclude_ambiguous || !AMBIGUOUS_CHARACTERS.contains(*c))
+        .collect();
+    chars.sort();
+    chars.dedup();
+    chars
+}
+
+fn pick<R: Rng>(rng: &mut R, alphabet: &[char]) -> char {
+    *rng.choose(alphabet).expect("non-empty alphabet")
+}
+
+fn password<R: Rng>(
+    rng: &mut R,
+    length: usize,
+    classes: &[CharacterClass],
+    exclude_ambiguous: bool,
+) -> Result<String, Error> {
+    if classes.is_empty() {
+        bail!("At least one character class is needed to generate a password.")
+    }
+    if length < classes.len() {
+        bail!(
+            "A password of length {} cannot contain characters of all {} requested classes.",
+            length,
+            classes.len()
+        )
+    }
+    let all = alphabet_of(classes, exclude_ambiguous);
+    let mut chars: Vec<char> = classes
+        .iter()
+        .map(|c| pick(rng, &alphabet_of(&[*c], exclude_ambiguous)))
+        .collect();
+    while chars.len() < length {
+        chars.push(pick(rng, &all));
+    }
+    rng.shuffle(&mut chars);
+    Ok(chars.into_iter().collect())
+}
+
+impl Generator {
+    pub fn generate(&self) -> Result<String, Error> {
+        let mut rng = OsRng::new().context("Could not obtain a secure source of randomness.")?;
+        self.generate_with(&mut rng)
+    }
+
+    fn generate_with<R: Rng>(&self, rng: &mut R) -> Result<String, Error> {
+        Ok(match *self {
+            Generator::Password {
+                length,
+                ref classes,
+                exclude_ambiguous,
+            } => password(rng, length, classes, exclude_ambiguous)?,
+            Generator::Passphrase { words, ref separator } => {
+                if words == 0 {
+                    bail!("A passphrase needs at least one word.")
+                }
+                let wordlist: Vec<_> = WORDLIST.lines().collect();
+                (0..words)
+                    .map(|_| *rng.choose(&wordlist).expect("non-empty word list"))
+                    .collect::<Vec<_>>()
+                    .join(separator)
+            }
+            Generator::Hex { bytes } | Generator::Base64 { bytes } => {
+                if bytes == 0 {
+                    bail!("A token needs at least one byte.")
+                }
+                let mut buf = vec![0u8; bytes];
+                rng.fill_bytes(&mut buf);
+                match *self {
+                    Generator::Hex { .. } => buf.iter().map(|b| format!("{:02x}", b)).collect(),
+                    _ => base64::encode(&buf),
+                }
+            }
+            Generator::Template {
+                ref template,
+                exclude_ambiguous,
+            } => {
+                let mut out = String::new();
+                for part in parse_template(template)? {
+                    match part {
+                        TemplatePart::Literal(s) => out.push_str(&s),
+                        TemplatePart::Random { length, class } => {
+                            let alphabet = alphabet_of(&[class], exclude_ambiguous);
+                            for _ in 0..length {
+                                out.push(pick(rng, &alphabet));
+                            }
+                        }
+                    }
+                }
+                out
+            }
+        })
+    }
+}
+
+impl Vault {
+    pub fn generate(
+        &self,
+        path: &Path,
+        generator: &Generator,
+        mode: WriteMode,
+        print: bool,
+        output: &mut Write,
+        error: &mut Write,
+    ) -> Result<(), Error> {
+        let secret = generator.generate()?;
+        self.encrypt(
+            &[
+                VaultSpec {
+                    src: SpecSourceType::Buffer(secret.as_bytes().to_owned()),
+                    dst: path.to_owned(),
+                },
+            ],
+            mode,
+            Destination::ReolveAndAppendGpg,
+            output,
+            error,
+        ).context(format!("Failed to store generated secret at '{}'.", path.display()))?;
+        if print {
+            writeln!(output, "{}", secret).ok();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_generate {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+
+    fn rng() -> StdRng {
+        StdRng::from_seed(&[42usize][..])
+    }
+
+    #[test]
+    fn it_parses_templates_with_literals_and_placeholders() {
+        assert_eq!(
+            parse_template("a-{{16:alnum}}-{{4:digit}}").unwrap(),
+            vec![
+                TemplatePart::Literal("a-".into()),
+                TemplatePart::Random {
+                    length: 16,
+                    class: CharacterClass::Alnum,
+                },
+                TemplatePart::Literal("-".into()),
+                TemplatePart::Random {
+                    length: 4,
+                    class: CharacterClass::Digit,
+                },
+            ]
+        )
+    }
+
+    #[test]
+    fn it_rejects_unterminated_placeholders() {
+        assert_eq!(
+            format!("{}", parse_template("{{4:digit").unwrap_err()),
+            "Unterminated placeholder in template '{{4:digit'"
+        )
+    }
+
+    #[test]
+    fn it_rejects_unknown_character_classes() {
+        assert_eq!(
+            format!("{}", parse_template("{{4:foo}}").unwrap_err()),
+            "Unknown character class: 'foo'"
+        )
+    }
+
+    #[test]
+    fn it_generates_passwords_containing_every_requested_class() {
+        let classes = [CharacterClass::Lower, CharacterClass::Digit, CharacterClass::Symbol];
+        let pw = password(&mut rng(), 3, &classes, false).unwrap();
+        assert_eq!(pw.chars().count(), 3);
+        for class in &classes {
+            assert!(pw.chars().any(|c| class.characters().contains(c)));
+        }
+    }
+
+    #[test]
+    fn it_excludes_ambiguous_characters_if_asked() {
+        let pw = password(&mut rng(), 256, &[CharacterClass::Alnum], true).unwrap();
+        assert!(!pw.chars().any(|c| AMBIGUOUS_CHARACTERS.contains(c)));
+    }
+
+    #[test]
+    fn it_fails_if_the_password_is_too_short_for_all_classes() {
+        assert!(password(&mut rng(), 1, &[CharacterClass::Lower, CharacterClass::Upper], false).is_err());
+    }
+
+    #[test]
+    fn it_generates_templates_and_tokens_of_the_expected_shape() {
+        let secret = Generator::Template {
+            template: "{{16:alnum}}-{{4:digit}}".into(),
+            exclude_ambiguous: false,
+        }.generate_with(&mut rng())
+            .unwrap();
+        assert_eq!(secret.len(), 21);
+        assert!(secret[17..].chars().all(|c| c.is_digit(10)));
+
+        let hex = Generator::Hex { bytes: 8 }.generate_with(&mut rng()).unwrap();
+        assert_eq!(hex.len(), 16);
+
+        let phrase = Generator::Passphrase {
+            words: 5,
+            separator: " ".into(),
+        }.generate_with(&mut rng())
+            .unwrap();
+        assert_eq!(phrase.split(' ').count(), 5);
+    }
+}
@@ -9,6 +9,7 @@ use spec::WriteMode;
 use std::io::Write;
 use util::export_key_with_progress;
 use TrustModel;
+use KeyPolicy;
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum DirectoryInfo {
@@ -41,6 +42,7 @@ impl Vault {
         name: Option<String>,
         trust_model: Option<TrustModel>,
         auto_import: Option<bool>,
+        key_policy: Option<KeyPolicy>,
         output: &mut Write,
     ) -> Result<Self, Error> {
         let vault = Vault {
@@ -50,11 +52,15 @@ impl Vault {
             secrets: secrets.to_owned(),
             auto_import,
             trust_model,
+            key_policy,
             ..Default::default()
         }.set_resolved_at(vault_path)?;
 
         let mut gpg_ctx = new_context()?;
         let keys = extract_at_least_one_secret_key(&mut gpg_ctx, gpg_key_ids)?;
+        if let Some(ref policy) = vault.key_policy {
+            policy.enforce(&mut gpg_ctx, &keys)?;
+        }
         vault.to_file(vault_path, WriteMode::RefuseOverwrite)?;
 
         let gpg_keys_dir = vault.absolute_path(gpg_keys_dir);
@@ -1,4 +1,7 @@
 extern crate atty;
+extern crate base32;
+extern crate base64;
+extern crate chrono;
 extern crate conv;
 #[macro_use]
 extern crate failure;
@@ -6,15 +9,24 @@ extern crate failure;
 extern crate failure_derive;
 extern crate glob;
 extern crate gpgme;
+extern crate hmac;
 #[macro_use]
 extern crate itertools;
 #[macro_use]
 extern crate lazy_static;
+extern crate libc;
 extern crate mktemp;
+extern crate rand;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 extern crate serde_yaml;
+extern crate sha1;
+extern crate sha2;
+extern crate sheesy_tools as tools;
+extern crate signal_hook;
+extern crate url;
 extern crate yaml_rust;
 
 pub mod error;
@@ -25,7 +37,27 @@ mod spec;
 mod init;
 mod resource;
 mod partitions;
+mod generate;
+mod document;
+mod record;
+mod otp;
+mod tempfile;
+mod editor;
+mod templates;
+mod prompt;
+mod versions;
+mod metadata;
+mod stale;
+mod tags;
 
 pub use spec::*;
 pub use base::{TrustModel, Vault, VaultExt};
+pub use generate::{CharacterClass, Generator};
 pub use util::print_causes;
+pub use editor::Editor;
+pub use prompt::prompt_for_secrets;
+pub use versions::Version;
+pub use metadata::Metadata;
+pub use tags::TagChange;
+pub use recipients::{KeyPolicy, PartitionRecipients, RecipientInfo};
+pub use tools::process::OutputMode;
@@ -0,0 +1,266 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use base::Vault;
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use error::DecryptionError;
+use failure::{Error, ResultExt};
+use gpgme;
+use serde_yaml;
+use util::{new_context, strip_ext, write_at};
+
+/// The directory within the secrets directory of a vault which holds the metadata of resources.
+pub const METADATA_DIR: &str = ".meta";
+
+pub const CREATED: &str = "created";
+pub const UPDATED: &str = "updated";
+/// A date like `2018-12-31` after which the resource should be rotated.
+pub const ROTATE_AFTER: &str = "rotate_after";
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Arbitrary fields describing a resource, like its `owner` or `description`.
+pub type Metadata = BTreeMap<String, String>;
+
+/// True if `path`, relative to a secrets directory, points into the metadata directory, which holds no resources.
+pub fn is_metadata_path(path: &Path) -> bool {
+    path.starts_with(METADATA_DIR)
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+fn now() -> String {
+    format_time(Utc::now())
+}
+
+fn metadata_path_of(secrets_dir: &Path, resource: &Path) -> PathBuf {
+    let mut file_name = resource.file_name().expect("resource with file name").to_owned();
+    file_name.push(".yml");
+    secrets_dir.join(METADATA_DIR).join(resource).with_file_name(file_name)
+}
+
+fn encrypted_path_of(clear_path: &Path) -> PathBuf {
+    let mut file_name = clear_path.file_name().expect("file name").to_owned();
+    file_name.push(".gpg");
+    clear_path.with_file_name(file_name)
+}
+
+fn decrypt_file(path: &Path) -> Result<Vec<u8>, Error> {
+    let mut ctx = new_context()?;
+    let mut input = File::open(path).context(format!("Could not open '{}' for reading.", path.display()))?;
+    let mut output = Vec::new();
+    ctx.decrypt(&mut input, &mut output)
+        .map_err(|e: gpgme::Error| DecryptionError::caused_by(e, "Failed to decrypt metadata."))?;
+    Ok(output)
+}
+
+fn validate(name: &str, value: &str) -> Result<(), Error> {
+    if name == ROTATE_AFTER && !value.is_empty() {
+        NaiveDate::parse_from_str(value, DATE_FORMAT).context(format!(
+            "'{}' must be a date like '2018-12-31', got '{}'",
+            ROTATE_AFTER, value
+        ))?;
+    }
+    Ok(())
+}
+
+impl Vault {
+    pub fn find_encrypt_metadata(&self, partition: &Vault) -> bool {
+        partition.encrypt_metadata.or(self.encrypt_metadata).unwrap_or(false)
+    }
+
+    /// The partition owning the resource at `path`, and the resource path relative to its secrets directory.
+    pub fn resource_of(&self, path: &Path) -> Result<(&Vault, PathBuf), Error> {
+        let (partition, path) = self.partition_by_owned_path(path.to_owned())?;
+        let path = match path.extension() {
+            Some(ext) if ext == "gpg" => strip_ext(&path),
+            _ => path,
+        };
+        let path = path.strip_prefix(partition.secrets_path())
+            .map(ToOwned::to_owned)
+            .unwrap_or(path);
+        Ok((partition, path))
+    }
+
+    pub fn read_metadata(&self, partition: &Vault, resource: &Path) -> Result<Option<Metadata>, Error> {
+        let clear_path = metadata_path_of(&partition.secrets_path(), resource);
+        let encrypted_path = encrypted_path_of(&clear_path);
+        let content = if encrypted_path.is_file() {
+            decrypt_file(&encrypted_path)?
+        } else if clear_path.is_file() {
+            fs::read(&clear_path).context(format!("Could not read metadata at '{}'.", clear_path.display()))?
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(serde_yaml::from_slice(&content).context(format!(
+            "Could not parse metadata of '{}'.",
+            resource.display()
+        ))?))
+    }
+
+    pub fn write_metadata(&self, partition: &Vault, resource: &Path, metadata: &Metadata) -> Result<(), Error> {
+        let clear_path = metadata_path_of(&partition.secrets_path(), resource);
+        let encrypted_path = encrypted_path_of(&clear_path);
+        let yaml = serde_yaml::to_vec(metadata).context("Could not serialize metadata.")?;
+        let (path, content, other_path) = if self.find_encrypt_metadata(partition) {
+            let mut zero = Vec::new();
+            let encrypted = self.encrypt_buffer(
+                partition,
+                &yaml,
+                self.gpg_keys_dir_for_auto_import(partition)
+                    .as_ref()
+                    .map(PathBuf::as_path),
+                &mut zero,
+            )?;
+            (encrypted_path, encrypted, clear_path)
+        } else {
+            (clear_path, yaml, encrypted_path)
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context(format!("Could not create metadata directory at '{}'.", dir.display()))?;
+        }
+        write_at(&path)
+            .and_then(|mut f| f.write_all(&content))
+            .context(format!("Could not write metadata to '{}'.", path.display()))?;
+        if other_path.is_file() {
+            fs::remove_file(&other_path).context(format!(
+                "Could not remove outdated metadata at '{}'.",
+                other_path.display()
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Remove the metadata of the resource whose encrypted file is at `encrypted_path`.
+    pub fn remove_metadata(&self, partition: &Vault, encrypted_path: &Path) -> Result<(), Error> {
+        let secrets_dir = partition.secrets_path();
+        let resource = match encrypted_path.strip_prefix(&secrets_dir) {
+            Ok(resource) => strip_ext(resource),
+            Err(_) => return Ok(()),
+        };
+        let clear_path = metadata_path_of(&secrets_dir, &resource);
+        for path in &[encrypted_path_of(&clear_path), clear_path] {
+            if path.is_file() {
+                fs::remove_file(path).context(format!("Failed to remove metadata at '{}'.", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Record that the resource was just written, if it has metadata.
+    pub fn touch_metadata(&self, partition: &Vault, resource: &Path) -> Result<(), Error> {
+        if let Some(mut metadata) = self.read_metadata(partition, resource)? {
+            metadata.insert(UPDATED.into(), now());
+            self.write_metadata(partition, resource, &metadata)?;
+        }
+        Ok(())
+    }
+
+    /// Record that the resource was last written at `time`, in seconds since the unix epoch, unless its metadata
+    /// knows already. This way, re-encrypting it for other recipients doesn't look like a change.
+    /// Resources without metadata are left alone.
+    pub fn keep_update_time(&self, partition: &Vault, resource: &Path, time: u64) -> Result<(), Error> {
+        if let Some(mut metadata) = self.read_metadata(partition, resource)? {
+            if !metadata.contains_key(UPDATED) {
+                metadata.insert(UPDATED.into(), format_time(Utc.timestamp(time as i64, 0)));
+                self.write_metadata(partition, resource, &metadata)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn print_metadata(&self, path: &Path, fields: &[String], output: &mut Write) -> Result<(), Error> {
+        let (partition, resource) = self.resource_of(path)?;
+        let metadata = self.read_metadata(partition, &resource)?
+            .ok_or_else(|| format_err!("There is no metadata for '{}'.", path.display()))?;
+        if fields.is_empty() {
+            for (name, value) in &metadata {
+                writeln!(output, "{}: {}", name, value)?;
+            }
+        } else {
+            for field in fields {
+                let value = metadata
+                    .get(field)
+                    .ok_or_else(|| format_err!("There is no metadata field named '{}' for '{}'.", field, path.display()))?;
+                writeln!(output, "{}", value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the metadata fields given as `(name, value)` pairs. Empty values remove the field.
+    pub fn set_metadata(&self, path: &Path, assignments: &[(String, String)], output: &mut Write) -> Result<(), Error> {
+        let (partition, resource) = self.resource_of(path)?;
+        let resource_path = partition.secrets_path().join(&resource);
+        let resource_path = encrypted_path_of(&resource_path);
+        if !resource_path.is_file() {
+            bail!("There is no resource at '{}'.", path.display())
+        }
+        let mut metadata = match self.read_metadata(partition, &resource)? {
+            Some(metadata) => metadata,
+            None => {
+                let mut metadata = Metadata::new();
+                metadata.insert(CREATED.into(), now());
+                metadata
+            }
+        };
+        for &(ref name, ref value) in assignments {
+            validate(name, value)?;
+            if value.is_empty() {
+                metadata.remove(name);
+            } else {
+                metadata.insert(name.to_owned(), value.to_owned());
+            }
+        }
+        self.write_metadata(partition, &resource, &metadata)?;
+        writeln!(output, "Updated metadata of '{}'.", path.display()).ok();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_metadata {
+    use super::*;
+    use mktemp::Temp;
+
+    #[test]
+    fn it_places_metadata_in_a_mirrored_hidden_directory() {
+        let clear_path = metadata_path_of(Path::new("/secrets"), Path::new("db/password"));
+        assert_eq!(clear_path, Path::new("/secrets/.meta/db/password.yml"));
+        assert_eq!(
+            encrypted_path_of(&clear_path),
+            Path::new("/secrets/.meta/db/password.yml.gpg")
+        );
+        assert!(is_metadata_path(Path::new(".meta/db/password.yml.gpg")));
+        assert!(!is_metadata_path(Path::new("db/.meta.gpg")));
+    }
+
+    #[test]
+    fn it_validates_rotation_dates() {
+        assert!(validate(ROTATE_AFTER, "2018-12-31").is_ok());
+        assert!(validate(ROTATE_AFTER, "tomorrow").is_err());
+        assert!(validate("owner", "tomorrow").is_ok());
+    }
+
+    #[test]
+    fn it_keeps_the_update_time_only_of_resources_with_metadata() {
+        let tmp = Temp::new_dir().unwrap();
+        let vault = Vault {
+            resolved_at: tmp.to_path_buf(),
+            ..Default::default()
+        };
+        vault.keep_update_time(&vault, Path::new("plain"), 0).unwrap();
+        assert!(!tmp.to_path_buf().join(METADATA_DIR).exists());
+
+        let clear_path = metadata_path_of(&vault.secrets_path(), Path::new("described"));
+        fs::create_dir_all(clear_path.parent().unwrap()).unwrap();
+        fs::write(&clear_path, "owner: ops\n").unwrap();
+        vault.keep_update_time(&vault, Path::new("described"), 0).unwrap();
+        let metadata = vault.read_metadata(&vault, Path::new("described")).unwrap().unwrap();
+        assert_eq!(metadata[UPDATED], "1970-01-01T00:00:00Z");
+        assert_eq!(metadata["owner"], "ops");
+    }
+}
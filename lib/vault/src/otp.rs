@@ -0,0 +1,235 @@
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base32;
+use base::Vault;
+use failure::{Error, ResultExt};
+use hmac::{Hmac, Mac};
+use record::Record;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use url::Url;
+
+const TOTP_URI_PREFIX: &str = "otpauth://totp/";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// The parameters of a time-based one-time password as defined in RFC 6238.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Totp {
+    secret: Vec<u8>,
+    digits: u32,
+    period: u64,
+    algorithm: Algorithm,
+}
+
+macro_rules! hmac {
+    ($digest:ty, $key:expr, $message:expr) => {{
+        let mut mac = Hmac::<$digest>::new_varkey($key).expect("HMAC to accept keys of any size");
+        mac.input($message);
+        mac.result().code().to_vec()
+    }};
+}
+
+impl Totp {
+    pub fn from_uri(uri: &str) -> Result<Self, Error> {
+        let url = Url::parse(uri).context(format!("Could not parse '{}' as otpauth URI", uri))?;
+        if url.scheme() != "otpauth" || url.host_str() != Some("totp") {
+            bail!("Only time-based one-time passwords are supported, expected URI to start with '{}'", TOTP_URI_PREFIX)
+        }
+        let mut totp = Totp {
+            secret: Vec::new(),
+            digits: 6,
+            period: 30,
+            algorithm: Algorithm::Sha1,
+        };
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "secret" => {
+                    totp.secret = base32::decode(
+                        base32::Alphabet::RFC4648 { padding: false },
+                        &value.trim_right_matches('=').to_uppercase(),
+                    ).ok_or_else(|| format_err!("The secret in the otpauth URI is not valid base32"))?
+                }
+                "digits" => {
+                    totp.digits = value
+                        .parse()
+                        .map_err(|_| format_err!("Invalid amount of digits: '{}'", value))?
+                }
+                "period" => {
+                    totp.period = value
+                        .parse()
+                        .map_err(|_| format_err!("Invalid period: '{}'", value))?
+                }
+                "algorithm" => {
+                    totp.algorithm = match value.to_uppercase().as_str() {
+                        "SHA1" => Algorithm::Sha1,
+                        "SHA256" => Algorithm::Sha256,
+                        "SHA512" => Algorithm::Sha512,
+                        _ => bail!("Unsupported algorithm: '{}'", value),
+                    }
+                }
+                _ => {}
+            }
+        }
+        if totp.secret.is_empty() {
+            bail!("The otpauth URI does not contain a secret")
+        }
+        if totp.digits < 1 || totp.digits > 9 {
+            bail!("The amount of digits must be between 1 and 9, got {}", totp.digits)
+        }
+        if totp.period == 0 {
+            bail!("The period must not be zero")
+        }
+        Ok(totp)
+    }
+
+    pub fn code_at(&self, unix_time: u64) -> String {
+        let counter = unix_time / self.period;
+        let mut message = [0u8; 8];
+        for (i, byte) in message.iter_mut().enumerate() {
+            *byte = (counter >> (56 - i * 8)) as u8;
+        }
+        let digest = match self.algorithm {
+            Algorithm::Sha1 => hmac!(Sha1, &self.secret, &message),
+            Algorithm::Sha256 => hmac!(Sha256, &self.secret, &message),
+            Algorithm::Sha512 => hmac!(Sha512, &self.secret, &message),
+        };
+        let offset = (digest[digest.len() - 1] & 0xf) as usize;
+        let binary = (u32::from(digest[offset]) & 0x7f) << 24 | u32::from(digest[offset + 1]) << 16
+            | u32::from(digest[offset + 2]) << 8 | u32::from(digest[offset + 3]);
+        format!(
+            "{:0width$}",
+            binary % 10u32.pow(self.digits),
+            width = self.digits as usize
+        )
+    }
+
+    pub fn seconds_remaining_at(&self, unix_time: u64) -> u64 {
+        self.period - unix_time % self.period
+    }
+}
+
+fn find_totp_uri(content: &str, field: Option<&str>) -> Result<String, Error> {
+    match field {
+        Some(name) => Record::from(content)
+            .get(name)
+            .map(|v| v.trim().to_owned())
+            .ok_or_else(|| format_err!("There is no field named '{}'", name)),
+        None => content
+            .lines()
+            .map(str::trim)
+            .find(|l| l.starts_with(TOTP_URI_PREFIX))
+            .map(ToOwned::to_owned)
+            .ok_or_else(|| format_err!("Did not find a line starting with '{}'", TOTP_URI_PREFIX)),
+    }
+}
+
+impl Vault {
+    pub fn otp(&self, path: &Path, field: Option<&str>, output: &mut Write, error: &mut Write) -> Result<(), Error> {
+        let mut buf = Vec::new();
+        self.decrypt(path, &mut buf)?;
+        let content = String::from_utf8(buf).context(format!(
+            "The resource at '{}' is not valid UTF-8 and cannot contain an otpauth URI.",
+            path.display()
+        ))?;
+        let totp = find_totp_uri(&content, field)
+            .and_then(|uri| Totp::from_uri(&uri))
+            .context(format!(
+                "Could not obtain one-time password parameters from '{}'.",
+                path.display()
+            ))?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("The system time is before the unix epoch.")?
+            .as_secs();
+        writeln!(output, "{}", totp.code_at(now))?;
+        writeln!(error, "Valid for another {}s", totp.seconds_remaining_at(now)).ok();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_totp {
+    use super::*;
+
+    fn totp(secret: &[u8], algorithm: Algorithm) -> Totp {
+        Totp {
+            secret: secret.to_owned(),
+            digits: 8,
+            period: 30,
+            algorithm,
+        }
+    }
+
+    #[test]
+    fn it_produces_the_rfc_6238_test_vectors() {
+        let sha1 = totp(b"12345678901234567890", Algorithm::Sha1);
+        let sha256 = totp(b"12345678901234567890123456789012", Algorithm::Sha256);
+        let sha512 = totp(
+            b"1234567890123456789012345678901234567890123456789012345678901234",
+            Algorithm::Sha512,
+        );
+        for &(time, expected_sha1, expected_sha256, expected_sha512) in &[
+            (59, "94287082", "46119246", "90693936"),
+            (1_111_111_109, "07081804", "68084774", "25091201"),
+            (1_111_111_111, "14050471", "67062674", "99943326"),
+            (1_234_567_890, "89005924", "91819424", "93441116"),
+            (2_000_000_000, "69279037", "90698825", "38618901"),
+            (20_000_000_000, "65353130", "77737706", "47863826"),
+        ] {
+            assert_eq!(sha1.code_at(time), expected_sha1);
+            assert_eq!(sha256.code_at(time), expected_sha256);
+            assert_eq!(sha512.code_at(time), expected_sha512);
+        }
+    }
+
+    #[test]
+    fn it_parses_otpauth_uris_with_all_parameters() {
+        let t = Totp::from_uri(
+            "otpauth://totp/ACME:bob@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+             &issuer=ACME&algorithm=SHA256&digits=8&period=60",
+        ).unwrap();
+        assert_eq!(
+            t,
+            Totp {
+                secret: b"12345678901234567890".to_vec(),
+                digits: 8,
+                period: 60,
+                algorithm: Algorithm::Sha256,
+            }
+        );
+        assert_eq!(t.seconds_remaining_at(61), 59);
+    }
+
+    #[test]
+    fn it_uses_defaults_for_missing_parameters() {
+        let t = Totp::from_uri("otpauth://totp/x?secret=gezdgnbvgy3tqojq").unwrap();
+        assert_eq!((t.digits, t.period, t.algorithm), (6, 30, Algorithm::Sha1));
+        assert_eq!(t.code_at(59).len(), 6);
+    }
+
+    #[test]
+    fn it_rejects_counter_based_uris() {
+        assert!(Totp::from_uri("otpauth://hotp/x?secret=GEZDGNBV&counter=1").is_err());
+    }
+
+    #[test]
+    fn it_finds_the_uri_in_records_and_named_fields() {
+        let content = "password\nuser: bob\notpauth://totp/x?secret=GEZDGNBV\ntotp: otpauth://totp/y?secret=ABC\n";
+        assert_eq!(
+            find_totp_uri(content, None).unwrap(),
+            "otpauth://totp/x?secret=GEZDGNBV"
+        );
+        assert_eq!(
+            find_totp_uri(content, Some("totp")).unwrap(),
+            "otpauth://totp/y?secret=ABC"
+        );
+    }
+}
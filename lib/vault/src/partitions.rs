@@ -115,6 +115,14 @@ impl Vault {
             recipients: recipients_file,
             trust_model: None,
             auto_import: None,
+            templates: None,
+            keep_versions: None,
+            encrypt_metadata: None,
+            required_certifications: None,
+            key_policy: None,
+            signed_recipients: None,
+            groups: None,
+            shared_groups: self.shared_groups.clone(),
         };
 
         let partition = new_partition.clone();
@@ -124,6 +132,9 @@ impl Vault {
         {
             let mut gpg_ctx = new_context()?;
             let keys = extract_at_least_one_secret_key(&mut gpg_ctx, gpg_key_ids)?;
+            if let Some(policy) = self.find_key_policy(&partition) {
+                policy.enforce(&mut gpg_ctx, &keys)?;
+            }
             let mut fprs: Vec<_> = keys.iter().map(|k| fingerprint_of(k)).collect::<Result<_, _>>()?;
             assure_empty_directory_exists(&partition_secrets_dir).context("Cannot create secrets directory")?;
             partition.write_recipients_list(&mut fprs)?;
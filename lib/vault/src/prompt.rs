@@ -0,0 +1,156 @@
+use std::io::{self, stdin, BufRead, Read, Write};
+use std::mem;
+use std::os::unix::io::RawFd;
+
+use atty;
+use failure::{Error, ResultExt};
+use libc;
+use spec::{SpecSourceType, VaultSpec};
+use tempfile::TerminalGuard;
+
+/// Disables echoing of typed characters on the terminal at `fd` while alive.
+/// Echoing is enabled again even if the process is interrupted or terminated meanwhile.
+struct HiddenInput {
+    fd: RawFd,
+    original: libc::termios,
+    _guard: TerminalGuard,
+}
+
+impl HiddenInput {
+    fn of(fd: RawFd) -> io::Result<Self> {
+        unsafe {
+            let mut original: libc::termios = mem::zeroed();
+            if libc::tcgetattr(fd, &mut original) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let guard = TerminalGuard::new(fd, original);
+            let mut hidden = original;
+            hidden.c_lflag &= !libc::ECHO;
+            hidden.c_lflag |= libc::ECHONL;
+            if libc::tcsetattr(fd, libc::TCSANOW, &hidden) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(HiddenInput {
+                fd,
+                original,
+                _guard: guard,
+            })
+        }
+    }
+}
+
+impl Drop for HiddenInput {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(self.fd, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+fn read_hidden(prompt: &str, multiline: bool, error: &mut Write) -> Result<String, Error> {
+    write!(error, "{}", prompt)?;
+    error.flush()?;
+    let _hidden = HiddenInput::of(libc::STDIN_FILENO).context("Could not disable echoing of the secret on the terminal.")?;
+    let mut secret = String::new();
+    let stdin = stdin();
+    if multiline {
+        stdin.lock().read_to_string(&mut secret)?;
+        writeln!(error).ok();
+    } else {
+        stdin.lock().read_line(&mut secret)?;
+        let len = secret.trim_right_matches(|c| c == '\n' || c == '\r').len();
+        secret.truncate(len);
+    }
+    Ok(secret)
+}
+
+/// Ask `question` on the terminal connected to standard input, and return true if it was answered with yes.
+/// Without a terminal, nobody can answer, which counts as no.
+pub fn confirm(question: &str, error: &mut Write) -> Result<bool, Error> {
+    if !atty::is(atty::Stream::Stdin) {
+        return Ok(false);
+    }
+    write!(error, "{} [y/N] ", question)?;
+    error.flush()?;
+    let mut answer = String::new();
+    stdin().lock().read_line(&mut answer)?;
+    Ok(match answer.trim() {
+        "y" | "Y" | "yes" | "Yes" => true,
+        _ => false,
+    })
+}
+
+/// Ask for the secret of each spec reading from standard input twice, with echo disabled, and
+/// return specs with the secret as source.
+pub fn prompt_for_secrets(specs: &[VaultSpec], multiline: bool, error: &mut Write) -> Result<Vec<VaultSpec>, Error> {
+    if !atty::is(atty::Stream::Stdin) {
+        bail!("Standard input must be a terminal to prompt for secrets.")
+    }
+    let hint = if multiline { " (end with Ctrl-D)" } else { "" };
+    specs
+        .iter()
+        .map(|spec| {
+            if spec.src != SpecSourceType::Stdin {
+                return Ok(spec.clone());
+            }
+            let name = spec.destination().display();
+            let secret = read_hidden(&format!("Secret for '{}'{}: ", name, hint), multiline, error)?;
+            if secret.is_empty() {
+                bail!("Refusing to store an empty secret at '{}'.", name)
+            }
+            let confirmation = read_hidden(&format!("Repeat secret for '{}'{}: ", name, hint), multiline, error)?;
+            if secret != confirmation {
+                bail!("The secrets entered for '{}' did not match.", name)
+            }
+            Ok(VaultSpec {
+                src: SpecSourceType::Buffer(secret.into_bytes()),
+                dst: spec.dst.clone(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests_hidden_input {
+    use super::*;
+    use tempfile::restore_terminal;
+    use std::ptr;
+
+    fn echo_of(fd: RawFd) -> bool {
+        unsafe {
+            let mut settings: libc::termios = mem::zeroed();
+            assert_eq!(libc::tcgetattr(fd, &mut settings), 0);
+            settings.c_lflag & libc::ECHO != 0
+        }
+    }
+
+    #[test]
+    fn it_disables_echo_until_dropped_or_the_process_is_ended_by_a_signal() {
+        let (mut master, mut slave) = (0, 0);
+        assert_eq!(
+            unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) },
+            0
+        );
+        assert!(echo_of(slave));
+        {
+            let _hidden = HiddenInput::of(slave).unwrap();
+            assert!(!echo_of(slave));
+        }
+        assert!(echo_of(slave));
+
+        let hidden = HiddenInput::of(slave).unwrap();
+        restore_terminal();
+        assert!(echo_of(slave), "an interrupt or termination enables echo again");
+        drop(hidden);
+        unsafe {
+            libc::close(slave);
+            libc::close(master);
+        }
+    }
+
+    #[test]
+    fn it_fails_without_a_terminal() {
+        let file = ::std::fs::File::open("/dev/null").unwrap();
+        assert!(HiddenInput::of(::std::os::unix::io::AsRawFd::as_raw_fd(&file)).is_err());
+    }
+}
@@ -5,26 +5,70 @@ use util::{export_key, fingerprint_of, new_context, KeyDisplay, KeylistDisplay,
 use spec::SigningMode;
 use std::iter::once;
 use TrustModel;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use gpgme;
+use mktemp::Temp;
+use KeyPolicy;
 
 impl Vault {
+    /// Fail if the keys of `gpg_key_ids` violate `policy`, before any of them is imported from `gpg_keys_dir`.
+    /// Key files are inspected in a throwaway keyring, while keys without a file are looked up in yours.
+    fn enforce_policy_on_key_files(
+        &self,
+        ctx: &mut gpgme::Context,
+        policy: &KeyPolicy,
+        gpg_keys_dir: &Path,
+        gpg_key_ids: &[String],
+    ) -> Result<(), Error> {
+        let scratch_home = Temp::new_dir().context("Could not create a temporary gpg home directory.")?;
+        let mut scratch_ctx = new_context()?;
+        scratch_ctx.set_engine_home_dir(scratch_home.to_path_buf().to_string_lossy().into_owned())?;
+        let mut in_keyring = Vec::new();
+        for id in gpg_key_ids {
+            match self.read_fingerprint_file(id, gpg_keys_dir) {
+                Ok((key_path, content)) => {
+                    scratch_ctx.import(content).context(format!(
+                        "Could not read key file at '{}' to check it against the key policy.",
+                        key_path.display()
+                    ))?;
+                }
+                Err(_) => in_keyring.push(id.to_owned()),
+            }
+        }
+        let scratch_keys = scratch_ctx.find_keys(gpg_key_ids.iter().filter(|id| !in_keyring.contains(id)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        policy.enforce(&mut scratch_ctx, &scratch_keys)?;
+        if !in_keyring.is_empty() {
+            let keys = ctx.find_keys(&in_keyring)?.collect::<Result<Vec<_>, _>>()?;
+            policy.enforce(ctx, &keys)?;
+        }
+        Ok(())
+    }
+
     pub fn add_recipients(
         &self,
         gpg_key_ids: &[String],
         sign: SigningMode,
         signing_key_id: Option<&str>,
         partitions: &[String],
+        comment: Option<&str>,
         output: &mut Write,
+        error: &mut Write,
     ) -> Result<(), Error> {
         let mut gpg_ctx = new_context()?;
         let partitions: Vec<&Vault> = self.partitions_by_name_or_path(partitions)?;
         let has_multiple_partitions = !self.partitions.is_empty();
 
         for partition in partitions {
+            partition.verify_recipients_signature(&mut gpg_ctx)?;
             if let SigningMode::Public = sign {
                 let gpg_keys_dir = self.find_gpg_keys_dir().with_context(|_| {
                     "Adding unverified recipients requires you to use a vault that has the `gpg-keys` directory configured"
                 })?;
+                if let Some(policy) = self.find_key_policy(partition) {
+                    partition.enforce_policy_on_key_files(&mut gpg_ctx, policy, &gpg_keys_dir, gpg_key_ids)?;
+                }
                 let imported_gpg_keys_ids = partition.import_keys(&mut gpg_ctx, &gpg_keys_dir, gpg_key_ids, output)?;
                 let signing_key = partition
                     .find_signing_key(&mut gpg_ctx, signing_key_id)
@@ -68,6 +112,17 @@ impl Vault {
                 ));
             };
 
+            if let Some(policy) = self.find_key_policy(partition) {
+                policy.enforce(&mut gpg_ctx, &keys)?;
+            }
+
+            if let (SigningMode::None, TrustModel::VaultMembers) = (sign, self.find_trust_model(partition)) {
+                let signing_key = partition
+                    .find_signing_key(&mut gpg_ctx, signing_key_id)
+                    .context("The trust model 'vault-members' requires you to certify the recipients you add.")?;
+                partition.certify_keys(&mut gpg_ctx, &signing_key, &keys, output)?;
+            }
+
             if let Ok(gpg_keys_dir) = self.find_gpg_keys_dir() {
                 let mut buf = Vec::new();
                 for key in &keys {
@@ -81,20 +136,29 @@ impl Vault {
                 }
             }
 
-            let mut recipients = partition.recipients_list()?;
+            let mut recipients = partition.recipients_entries()?;
+            let mut comments = BTreeMap::new();
             for key in keys {
-                recipients.push(fingerprint_of(&key)?);
+                let fpr = fingerprint_of(&key)?;
+                if let Some(comment) = comment {
+                    comments.insert(fpr.clone(), comment.to_owned());
+                }
+                recipients.push(fpr);
                 writeln!(output, "Added recipient {}", KeyDisplay(&key)).ok();
             }
-            partition.write_recipients_list(&mut recipients)?;
+            partition.write_recipients_file(&mut recipients, &comments)?;
             partition.reencrypt(
+                self,
                 &mut gpg_ctx,
                 &self.find_trust_model(partition),
+                self.find_required_certifications(partition),
+                self.find_keep_versions(partition),
                 self.gpg_keys_dir_for_auto_import(partition)
                     .as_ref()
                     .map(PathBuf::as_ref),
                 has_multiple_partitions,
                 output,
+                error,
             )?;
         }
         Ok(())
@@ -0,0 +1,270 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use base::Vault;
+use failure::{Error, ResultExt};
+use gpgme::{self, Key, KeyListMode};
+use util::{export_key, fingerprint_of, new_context, UserIdFingerprint};
+
+pub const DEFAULT_REQUIRED_CERTIFICATIONS: usize = 1;
+
+/// Find the key with fingerprint `fpr` along with the certifications on its user ids, which gpg checked.
+fn find_key_with_certifications(ctx: &mut gpgme::Context, fpr: &str) -> Result<Key, Error> {
+    let previous_mode = ctx.key_list_mode();
+    // Without SIG_NOTATIONS, gpg lists certifications without checking them, and all would appear valid.
+    ctx.set_key_list_mode(previous_mode | KeyListMode::SIGS | KeyListMode::SIG_NOTATIONS)?;
+    let key = ctx.find_key(fpr);
+    ctx.set_key_list_mode(previous_mode)?;
+    Ok(key.context(format!("Could not find key {} in the gpg keyring.", fpr))?)
+}
+
+/// The fingerprints in `candidates` with a valid certification on any user id of `key`, which isn't revoked.
+fn certifiers<'a>(key: &Key, candidates: &'a [String]) -> Vec<&'a str> {
+    let fpr = key.fingerprint().unwrap_or("");
+    let signed_by = |key_id: &str, revocation: bool| {
+        key.user_ids().filter(|uid| !uid.is_revoked()).any(|uid| {
+            uid.signatures().any(|sig| {
+                sig.is_revocation() == revocation && !sig.is_invalid() && !sig.is_expired()
+                    && sig.status() == gpgme::Error::NO_ERROR
+                    && sig.signer_key_id()
+                        .map(|id| !id.is_empty() && key_id.ends_with(&id.to_uppercase()))
+                        .unwrap_or(false)
+            })
+        })
+    };
+    candidates
+        .iter()
+        .map(String::as_str)
+        .filter(|c| *c != fpr && signed_by(c, false) && !signed_by(c, true))
+        .collect()
+}
+
+fn num_trusted(certifiers: &[String], trusted: &[String]) -> usize {
+    certifiers.iter().filter(|c| trusted.contains(c)).count()
+}
+
+/// Trust each of the `candidates`, given along with the fingerprints of its certifiers, once `required` of its
+/// certifiers are `trusted`, and let it certify others from then on. Returns the candidates which remain uncertified.
+fn extend_trust(
+    trusted: &mut Vec<String>,
+    mut candidates: Vec<(String, Vec<String>)>,
+    required: usize,
+) -> Vec<(String, Vec<String>)> {
+    loop {
+        let (certified, remaining): (Vec<_>, Vec<_>) = candidates
+            .into_iter()
+            .partition(|&(_, ref certifiers)| num_trusted(certifiers, trusted) >= required);
+        candidates = remaining;
+        if certified.is_empty() {
+            return candidates;
+        }
+        for (fpr, _) in certified {
+            if !trusted.contains(&fpr) {
+                trusted.push(fpr);
+            }
+        }
+    }
+}
+
+impl Vault {
+    pub fn find_required_certifications(&self, partition: &Vault) -> usize {
+        partition
+            .required_certifications
+            .or(self.required_certifications)
+            .unwrap_or(DEFAULT_REQUIRED_CERTIFICATIONS)
+    }
+
+    /// Fail unless each of `keys` is certified by `required` trusted `keys`, or all others if there are fewer `keys`.
+    /// Keys you own the secret key for are exempt and trusted. The recipients you accepted last when verifying their
+    /// signature are trusted to certify others, but need to be certified themselves. From there, trust extends to
+    /// every key certified by enough trusted keys, so certifications among keys nobody trusts count for nothing.
+    /// Certifications exported into `gpg_keys_dir` are merged into the gpg keyring first.
+    pub fn verify_member_certifications(
+        &self,
+        ctx: &mut gpgme::Context,
+        keys: &[Key],
+        required: usize,
+        gpg_keys_dir: Option<&Path>,
+        output: &mut Write,
+    ) -> Result<(), Error> {
+        let members = keys.iter().map(fingerprint_of).collect::<Result<Vec<_>, _>>()?;
+        let required = required.min(members.len().saturating_sub(1));
+        if let Some(gpg_keys_dir) = gpg_keys_dir {
+            let exported: Vec<_> = members
+                .iter()
+                .filter(|fpr| gpg_keys_dir.join(fpr).is_file())
+                .cloned()
+                .collect();
+            self.import_keys(ctx, gpg_keys_dir, &exported, output)
+                .context("Could not import certifications of recipient keys.")?;
+        }
+        let accepted = self.trusted_recipients()?.unwrap_or_else(Vec::new);
+        let mut trusted = Vec::new();
+        let mut candidates = Vec::new();
+        for fpr in &members {
+            if ctx.find_secret_key(fpr.as_str()).is_ok() {
+                trusted.push(fpr.to_owned());
+                continue;
+            }
+            if accepted.iter().any(|a| fpr.ends_with(&a.to_uppercase())) {
+                trusted.push(fpr.to_owned());
+            }
+            let key = find_key_with_certifications(ctx, fpr)?;
+            let certified_by = certifiers(&key, &members).into_iter().map(ToOwned::to_owned).collect();
+            candidates.push((fpr.to_owned(), certified_by));
+        }
+        let untrusted = extend_trust(&mut trusted, candidates, required);
+
+        if !untrusted.is_empty() {
+            bail!(
+                "The trust model 'vault-members' requires recipient keys to be certified by trusted recipients \
+                 of '{}', which are yours, the ones you accepted last, and the ones certified by those:\n{}\n\
+                 Use 'recipients certify' to certify them.",
+                self.recipients_path().display(),
+                untrusted
+                    .iter()
+                    .map(|&(ref fpr, ref certified_by)| format!(
+                        "{} is certified by {} of {} required trusted recipient(s)",
+                        keys.iter()
+                            .zip(&members)
+                            .find(|&(_, m)| m == fpr)
+                            .map(|(key, _)| UserIdFingerprint(key).to_string())
+                            .unwrap_or_else(|| fpr.to_owned()),
+                        num_trusted(certified_by, &trusted),
+                        required
+                    ))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )
+        }
+        Ok(())
+    }
+
+    /// Certify all `keys` with `signing_key` unless that was done already.
+    pub fn certify_keys(
+        &self,
+        ctx: &mut gpgme::Context,
+        signing_key: &Key,
+        keys: &[Key],
+        output: &mut Write,
+    ) -> Result<(), Error> {
+        let signing_fpr = [fingerprint_of(signing_key)?];
+        ctx.clear_signers();
+        ctx.add_signer(signing_key)?;
+        for key in keys {
+            let fpr = fingerprint_of(key)?;
+            if !certifiers(&find_key_with_certifications(ctx, &fpr)?, &signing_fpr).is_empty() {
+                writeln!(output, "Key {} is already certified by you", UserIdFingerprint(key)).ok();
+                continue;
+            }
+            ctx.sign_key(key, None::<&[u8]>, None).context(format!(
+                "Could not certify key of recipient {} with signing key {}",
+                fpr,
+                UserIdFingerprint(signing_key)
+            ))?;
+            writeln!(
+                output,
+                "Certified key {} with signing key {}",
+                UserIdFingerprint(key),
+                UserIdFingerprint(signing_key)
+            ).ok();
+        }
+        ctx.clear_signers();
+        Ok(())
+    }
+
+    /// Certify the keys identified by `gpg_key_ids` with your key, which must be a recipient,
+    /// and export them along with the certification into the gpg keys directory.
+    pub fn certify_recipients(
+        &self,
+        gpg_key_ids: &[String],
+        signing_key_id: Option<&str>,
+        output: &mut Write,
+    ) -> Result<(), Error> {
+        let gpg_keys_dir = self.find_gpg_keys_dir().context(
+            "Certifying recipients requires a vault that has the `gpg-keys` directory configured, to share them",
+        )?;
+        let mut ctx = new_context()?;
+        let keys = self.keys_by_ids(
+            &mut ctx,
+            gpg_key_ids,
+            "user-id",
+            self.gpg_keys_dir_for_auto_import(self)
+                .as_ref()
+                .map(PathBuf::as_path),
+            output,
+        )?;
+        let signing_key = self.find_signing_key(&mut ctx, signing_key_id)
+            .context("Keys can only be certified by a recipient of the vault.")?;
+        let signing_fpr = fingerprint_of(&signing_key)?;
+        if let Some(key) = keys.iter().find(|k| fingerprint_of(k).ok().as_ref() == Some(&signing_fpr)) {
+            bail!("You cannot certify your own key {}.", UserIdFingerprint(key))
+        }
+        self.certify_keys(&mut ctx, &signing_key, &keys, output)?;
+
+        let mut buf = Vec::new();
+        for key in &keys {
+            let (_, key_path) = export_key(&mut ctx, &gpg_keys_dir, key, &mut buf)?;
+            writeln!(
+                output,
+                "Exported public key with certifications of {} to '{}'",
+                UserIdFingerprint(key),
+                key_path.display()
+            ).ok();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_certify {
+    use super::*;
+
+    fn candidate(fpr: &str, certifiers: &[&str]) -> (String, Vec<String>) {
+        (fpr.to_owned(), certifiers.iter().map(|&c| c.to_owned()).collect())
+    }
+
+    #[test]
+    fn it_extends_trust_along_chains_of_certifications() {
+        let mut trusted = vec!["ME".to_owned()];
+        let untrusted = extend_trust(&mut trusted, vec![candidate("C", &["B"]), candidate("B", &["ME"])], 1);
+        assert!(untrusted.is_empty());
+        assert_eq!(trusted, vec!["ME", "B", "C"]);
+    }
+
+    #[test]
+    fn it_does_not_trust_keys_which_only_certify_each_other() {
+        let mut trusted = vec!["ME".to_owned(), "B".to_owned()];
+        let untrusted = extend_trust(
+            &mut trusted,
+            vec![
+                candidate("X", &["Y", "Z"]),
+                candidate("Y", &["X", "Z"]),
+                candidate("Z", &["X", "Y"]),
+            ],
+            2,
+        );
+        assert_eq!(untrusted.len(), 3);
+        assert_eq!(trusted, vec!["ME", "B"]);
+    }
+
+    #[test]
+    fn it_requires_at_least_one_trusted_certifier_if_nobody_is_trusted() {
+        let mut trusted = Vec::new();
+        assert_eq!(extend_trust(&mut trusted, vec![candidate("X", &["Y"]), candidate("Y", &["X"])], 1).len(), 2);
+    }
+
+    #[test]
+    fn it_does_not_lower_the_required_certifications_if_few_keys_are_trusted() {
+        let mut trusted = vec!["ME".to_owned()];
+        let untrusted = extend_trust(&mut trusted, vec![candidate("B", &["ME"]), candidate("C", &["ME", "B"])], 2);
+        assert_eq!(untrusted.len(), 2);
+    }
+
+    #[test]
+    fn it_requires_accepted_recipients_to_be_certified_as_well() {
+        let mut trusted = vec!["A".to_owned(), "B".to_owned()];
+        let untrusted = extend_trust(&mut trusted, vec![candidate("A", &[]), candidate("B", &["A"])], 1);
+        assert_eq!(untrusted, vec![candidate("A", &[])]);
+    }
+}
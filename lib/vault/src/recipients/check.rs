@@ -0,0 +1,219 @@
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::iter::once;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use base::Vault;
+use failure::Error;
+use gpgme;
+use util::new_context;
+use super::info::RecipientInfo;
+
+/// Keys expiring within this amount of days cause a warning whenever they are used to encrypt.
+pub const DEFAULT_WARN_DAYS: u64 = 30;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+lazy_static! {
+    static ref WARNED_FINGERPRINTS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+}
+
+impl RecipientInfo {
+    /// Everything that prevents the key from being used for encryption, or will do so within `warn_days`.
+    pub fn problems(&self, now: SystemTime, warn_days: u64) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.revoked {
+            problems.push("revoked".to_owned());
+        }
+        match (self.expires_at, self.expires.as_ref()) {
+            (Some(expires_at), Some(expires)) => match expires_at.duration_since(now) {
+                Ok(left) => {
+                    let days_left = left.as_secs() / SECONDS_PER_DAY;
+                    if days_left < warn_days {
+                        problems.push(format!("expires on {} (in {} days)", expires, days_left));
+                    }
+                }
+                Err(_) => problems.push(format!("expired on {}", expires)),
+            },
+            _ if self.expired => problems.push("expired".to_owned()),
+            _ => {}
+        }
+        // The key can't encrypt anymore once all of its encryption subkeys expired, even if the primary key is valid.
+        if let (Some(expires_at), Some(expires)) = (self.encryption_expires_at, self.encryption_expires.as_ref()) {
+            let expires_first = self.expires_at.map_or(true, |primary| expires_at < primary);
+            if let (true, Ok(left)) = (expires_first, expires_at.duration_since(now)) {
+                let days_left = left.as_secs() / SECONDS_PER_DAY;
+                if days_left < warn_days {
+                    problems.push(format!(
+                        "has encryption subkeys expiring on {} (in {} days)",
+                        expires, days_left
+                    ));
+                }
+            }
+        }
+        if !self.can_encrypt && !self.revoked && !self.expired {
+            problems.push("has no usable encryption subkey".to_owned());
+        }
+        problems
+    }
+}
+
+/// Print a warning for each of `keys` which will expire soon, once per key and process.
+pub fn warn_about_expiring_keys(keys: &[gpgme::Key], output: &mut Write) {
+    let now = SystemTime::now();
+    let mut warned = WARNED_FINGERPRINTS.lock().expect("no poisoned lock");
+    for key in keys {
+        let info = RecipientInfo::from(key);
+        let problems = info.problems(now, DEFAULT_WARN_DAYS);
+        if problems.is_empty() || !warned.insert(info.fingerprint.clone()) {
+            continue;
+        }
+        writeln!(
+            output,
+            "WARNING: The key of recipient {} ({}) {}. Run 'recipients check' for details.",
+            info.fingerprint,
+            info.user_id.as_ref().map(String::as_str).unwrap_or("[none]"),
+            problems.join(", ")
+        ).ok();
+    }
+}
+
+impl Vault {
+    /// Print all recipient keys of all partitions which are unusable, expire within `warn_days`
+    /// or violate the key policy.
+    /// Fails if there is at least one such key.
+    pub fn check_recipients(&self, warn_days: u64, output: &mut Write, error: &mut Write) -> Result<(), Error> {
+        let now = SystemTime::now();
+        let mut ctx = new_context()?;
+        let mut num_keys = 0;
+        let mut num_problematic = 0;
+        for partition in once(self).chain(self.partitions.iter()) {
+            let keys = partition.recipient_keys(
+                &mut ctx,
+                self.gpg_keys_dir_for_auto_import(partition)
+                    .as_ref()
+                    .map(PathBuf::as_path),
+                error,
+            )?;
+            let mut printed_partition = false;
+            for key in &keys {
+                num_keys += 1;
+                let info = RecipientInfo::from(key);
+                let mut problems = info.problems(now, warn_days);
+                if let Some(policy) = self.find_key_policy(partition) {
+                    problems.extend(policy.violations(&mut ctx, key, now)?);
+                }
+                if problems.is_empty() {
+                    continue;
+                }
+                if !printed_partition && !self.partitions.is_empty() {
+                    writeln!(output, "{}", partition.url())?;
+                    printed_partition = true;
+                }
+                writeln!(
+                    output,
+                    "{} ({})\t{}",
+                    info.fingerprint,
+                    info.user_id.as_ref().map(String::as_str).unwrap_or("[none]"),
+                    problems.join(", ")
+                )?;
+                num_problematic += 1;
+            }
+        }
+        if num_problematic > 0 {
+            bail!("{} recipient key(s) need attention.", num_problematic)
+        }
+        writeln!(
+            output,
+            "All {} recipient key(s) are usable for at least {} more days.",
+            num_keys, warn_days
+        ).ok();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_check {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn info(expires_in_days: Option<u64>) -> RecipientInfo {
+        RecipientInfo {
+            fingerprint: "905E53FE2FC0A500100AB80B056F92A52DF04D4E".into(),
+            user_id: None,
+            algorithm: "rsa2048".into(),
+            length: 2048,
+            created: None,
+            expires: expires_in_days.map(|_| "2018-06-01".into()),
+            revoked: false,
+            expired: false,
+            can_encrypt: true,
+            groups: Vec::new(),
+            expires_at: expires_in_days.map(|d| now() + Duration::from_secs(d * SECONDS_PER_DAY + 1)),
+            encryption_expires: None,
+            encryption_expires_at: None,
+        }
+    }
+
+    fn now() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1000 * SECONDS_PER_DAY)
+    }
+
+    #[test]
+    fn it_ignores_keys_which_expire_later_or_never() {
+        assert!(info(None).problems(now(), 30).is_empty());
+        assert!(info(Some(30)).problems(now(), 30).is_empty());
+    }
+
+    #[test]
+    fn it_reports_encryption_subkeys_expiring_before_the_primary_key() {
+        let subkey_expiring_soon = RecipientInfo {
+            encryption_expires: Some("2018-05-01".into()),
+            encryption_expires_at: Some(now() + Duration::from_secs(5 * SECONDS_PER_DAY + 1)),
+            ..info(None)
+        };
+        assert_eq!(
+            subkey_expiring_soon.problems(now(), 30),
+            vec!["has encryption subkeys expiring on 2018-05-01 (in 5 days)"]
+        );
+        let expiring_with_primary_key = RecipientInfo {
+            encryption_expires_at: Some(now() + Duration::from_secs(10 * SECONDS_PER_DAY + 1)),
+            encryption_expires: Some("2018-06-01".into()),
+            ..info(Some(10))
+        };
+        assert_eq!(
+            expiring_with_primary_key.problems(now(), 30),
+            vec!["expires on 2018-06-01 (in 10 days)"]
+        );
+    }
+
+    #[test]
+    fn it_reports_keys_expiring_soon() {
+        assert_eq!(
+            info(Some(10)).problems(now(), 30),
+            vec!["expires on 2018-06-01 (in 10 days)"]
+        );
+    }
+
+    #[test]
+    fn it_reports_expired_revoked_and_unusable_keys() {
+        let mut expired = info(Some(0));
+        expired.expires_at = Some(now() - Duration::from_secs(1));
+        expired.expired = true;
+        assert_eq!(expired.problems(now(), 30), vec!["expired on 2018-06-01"]);
+
+        let revoked = RecipientInfo {
+            revoked: true,
+            can_encrypt: false,
+            ..info(None)
+        };
+        assert_eq!(revoked.problems(now(), 30), vec!["revoked"]);
+
+        let unusable = RecipientInfo {
+            can_encrypt: false,
+            ..info(None)
+        };
+        assert_eq!(unusable.problems(now(), 30), vec!["has no usable encryption subkey"]);
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+pub const COMMENT_PREFIX: char = '#';
+
+/// A recipient along with the comment lines above it and the comment on its line.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Entry {
+    leading: Vec<String>,
+    id: String,
+    comment: Option<String>,
+}
+
+/// The content of a recipients file, with one fingerprint or group reference per line.
+/// Blank lines and everything after a `#` are ignored, but retained when the file is rewritten.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RecipientsFile {
+    entries: Vec<Entry>,
+    trailing: Vec<String>,
+}
+
+impl RecipientsFile {
+    pub fn parse(content: &str) -> Self {
+        let mut file = RecipientsFile::default();
+        let mut leading = Vec::new();
+        for line in content.lines() {
+            let (id, comment) = match line.find(COMMENT_PREFIX) {
+                Some(pos) => (line[..pos].trim(), Some(line[pos + 1..].trim().to_owned())),
+                None => (line.trim(), None),
+            };
+            if id.is_empty() {
+                leading.push(line.trim_right().to_owned());
+                continue;
+            }
+            file.entries.push(Entry {
+                leading: leading.split_off(0),
+                id: id.to_owned(),
+                comment,
+            });
+        }
+        file.trailing = leading;
+        file
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.entries.iter().map(|e| e.id.clone()).collect()
+    }
+
+    /// The comment on the line of the recipient `id`, if there is one.
+    pub fn comment_of(&self, id: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|e| e.id == id)
+            .and_then(|e| e.comment.as_ref().map(String::as_str))
+    }
+
+    /// Make `ids` the recipients in the given order, keeping the comments of those which were present already.
+    /// Comments in `comments` replace existing ones.
+    pub fn set_ids(&mut self, ids: &[String], comments: &BTreeMap<String, String>) {
+        let mut previous = self.entries.split_off(0);
+        for id in ids {
+            let mut entry = match previous.iter().position(|e| e.id == *id) {
+                Some(pos) => previous.remove(pos),
+                None => Entry {
+                    leading: Vec::new(),
+                    id: id.to_owned(),
+                    comment: None,
+                },
+            };
+            if let Some(comment) = comments.get(id) {
+                entry.comment = Some(comment.to_owned());
+            }
+            self.entries.push(entry);
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for entry in &self.entries {
+            for line in &entry.leading {
+                out.push_str(line);
+                out.push('\n');
+            }
+            out.push_str(&entry.id);
+            if let Some(ref comment) = entry.comment {
+                out.push_str(&format!(" {} {}", COMMENT_PREFIX, comment));
+            }
+            out.push('\n');
+        }
+        for line in &self.trailing {
+            out.push_str(line);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests_file {
+    use super::*;
+
+    const CONTENT: &str = "# the admins\n\nBBBB # bob, TICKET-2\n# alice\nAAAA\n\n# end\n";
+
+    #[test]
+    fn it_ignores_comments_and_blank_lines() {
+        assert_eq!(RecipientsFile::parse(CONTENT).ids(), vec!["BBBB", "AAAA"]);
+    }
+
+    #[test]
+    fn it_provides_the_comment_on_the_line_of_a_recipient() {
+        let file = RecipientsFile::parse(CONTENT);
+        assert_eq!(file.comment_of("BBBB"), Some("bob, TICKET-2"));
+        assert_eq!(file.comment_of("AAAA"), None);
+        assert_eq!(file.comment_of("CCCC"), None);
+    }
+
+    #[test]
+    fn it_keeps_comments_with_their_recipient_when_rewriting() {
+        let mut file = RecipientsFile::parse(CONTENT);
+        let mut comments = BTreeMap::new();
+        comments.insert("CCCC".to_owned(), "carol".to_owned());
+        file.set_ids(&["AAAA".into(), "BBBB".into(), "CCCC".into()], &comments);
+        assert_eq!(
+            file.render(),
+            "# alice\nAAAA\n# the admins\n\nBBBB # bob, TICKET-2\nCCCC # carol\n\n# end\n"
+        );
+    }
+
+    #[test]
+    fn it_renders_a_file_without_comments_as_before() {
+        let mut file = RecipientsFile::default();
+        file.set_ids(&["AAAA".into(), "BBBB".into()], &BTreeMap::new());
+        assert_eq!(file.render(), "AAAA\nBBBB\n");
+    }
+}
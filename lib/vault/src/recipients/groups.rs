@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+use std::fs::remove_file;
+use std::io::Write;
+use std::iter::once;
+use std::path::PathBuf;
+
+use base::Vault;
+use failure::{Error, ResultExt};
+use util::{export_key, fingerprint_of, new_context, KeyDisplay};
+
+/// Named sets of fingerprints, which can be referenced in recipients files as `@<name>`.
+pub type Groups = BTreeMap<String, Vec<String>>;
+
+pub const GROUP_PREFIX: char = '@';
+
+/// Replace all references to groups in `entries` by the fingerprints of their members, recursively.
+fn expand<'a, F>(entries: &[String], lookup: &F, stack: &mut Vec<String>, out: &mut Vec<String>) -> Result<(), Error>
+where
+    F: Fn(&str) -> Option<&'a Vec<String>>,
+{
+    for entry in entries {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        if entry.starts_with(GROUP_PREFIX) {
+            let name = &entry[1..];
+            if stack.iter().any(|n| n == name) {
+                bail!(
+                    "Group '{}' contains itself through {}.",
+                    name,
+                    stack.iter().map(|n| format!("@{}", n)).collect::<Vec<_>>().join(" -> ")
+                )
+            }
+            let members = lookup(name).ok_or_else(|| format_err!("Group '{}' is not defined in the vault.", name))?;
+            stack.push(name.to_owned());
+            expand(members, lookup, stack, out)?;
+            stack.pop();
+        } else if !out.iter().any(|fpr| fpr == entry) {
+            out.push(entry.to_owned());
+        }
+    }
+    Ok(())
+}
+
+fn is_member(members: &[String], fpr: &str) -> bool {
+    members.iter().any(|m| m == fpr)
+}
+
+impl Vault {
+    /// Make the groups of all partitions available to each of them. The first definition of a group wins.
+    pub fn share_groups(&mut self) {
+        let mut shared = Groups::new();
+        for vault in self.all_in_order() {
+            for (name, members) in vault.groups.iter().flat_map(|g| g.iter()) {
+                shared.entry(name.to_owned()).or_insert_with(|| members.to_owned());
+            }
+        }
+        for partition in &mut self.partitions {
+            partition.shared_groups = shared.clone();
+        }
+        self.shared_groups = shared;
+    }
+
+    pub fn group_members(&self, name: &str) -> Option<&Vec<String>> {
+        self.groups
+            .as_ref()
+            .and_then(|g| g.get(name))
+            .or_else(|| self.shared_groups.get(name))
+    }
+
+    /// The fingerprints of all recipients, with groups replaced by their members.
+    pub fn recipients_list(&self) -> Result<Vec<String>, Error> {
+        let mut fingerprints = Vec::new();
+        expand(
+            &self.recipients_entries()?,
+            &|name| self.group_members(name),
+            &mut Vec::new(),
+            &mut fingerprints,
+        ).context(format!(
+            "Could not resolve the groups in the recipients file at '{}'.",
+            self.recipients_path().display()
+        ))?;
+        Ok(fingerprints)
+    }
+
+    /// The names of all groups `fpr` is a member of, directly or through another group.
+    pub fn groups_of(&self, fpr: &str) -> Vec<String> {
+        let mut names: Vec<_> = self.shared_groups
+            .keys()
+            .chain(self.groups.iter().flat_map(|g| g.keys()))
+            .filter(|name| {
+                let mut members = Vec::new();
+                expand(
+                    &[format!("{}{}", GROUP_PREFIX, name)],
+                    &|name| self.group_members(name),
+                    &mut Vec::new(),
+                    &mut members,
+                ).is_ok() && is_member(&members, fpr)
+            })
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Add the keys identified by `gpg_key_ids` to group `name`, or remove them if `add` is false,
+    /// and re-encrypt all partitions whose recipients changed.
+    pub fn change_group(
+        &self,
+        name: &str,
+        gpg_key_ids: &[String],
+        add: bool,
+        output: &mut Write,
+        error: &mut Write,
+    ) -> Result<(), Error> {
+        let mut ctx = new_context()?;
+        for partition in once(self).chain(self.partitions.iter()) {
+            partition.verify_recipients_signature(&mut ctx)?;
+        }
+        let mut changed = self.clone();
+        let keys = if add {
+            self.keys_by_ids(
+                &mut ctx,
+                gpg_key_ids,
+                "user-id",
+                self.gpg_keys_dir_for_auto_import(self)
+                    .as_ref()
+                    .map(PathBuf::as_path),
+                output,
+            )?
+        } else {
+            Vec::new()
+        };
+        let fprs: Vec<String> = if add {
+            keys.iter().map(fingerprint_of).collect::<Result<_, _>>()?
+        } else {
+            let members = self.group_members(name)
+                .ok_or_else(|| format_err!("Group '{}' is not defined in the vault.", name))?;
+            gpg_key_ids
+                .iter()
+                .map(|id| {
+                    members
+                        .iter()
+                        .find(|m| m.to_uppercase().ends_with(&id.to_uppercase()))
+                        .cloned()
+                        .ok_or_else(|| format_err!("'{}' is not a member of group '{}'.", id, name))
+                })
+                .collect::<Result<_, Error>>()?
+        };
+
+        {
+            let owner_index = once(&changed)
+                .chain(changed.partitions.iter())
+                .find(|v| v.groups.as_ref().map_or(false, |g| g.contains_key(name)))
+                .map_or(changed.index, |v| v.index);
+            let owner = if owner_index == changed.index {
+                &mut changed
+            } else {
+                changed
+                    .partitions
+                    .iter_mut()
+                    .find(|p| p.index == owner_index)
+                    .expect("partition to exist")
+            };
+            let groups = owner.groups.get_or_insert_with(Groups::new);
+            let members = groups.entry(name.to_owned()).or_insert_with(Vec::new);
+            for fpr in &fprs {
+                if add {
+                    if !is_member(members, fpr) {
+                        members.push(fpr.to_owned());
+                        writeln!(output, "Added {} to group '{}'", fpr, name).ok();
+                    }
+                } else {
+                    members.retain(|m| m != fpr);
+                    writeln!(output, "Removed {} from group '{}'", fpr, name).ok();
+                }
+            }
+            if members.is_empty() {
+                bail!("Cannot remove all members of group '{}'.", name)
+            }
+        }
+        changed.share_groups();
+
+        let mut affected = Vec::new();
+        for (before, after) in once(self)
+            .chain(self.partitions.iter())
+            .zip(once(&changed).chain(changed.partitions.iter()))
+        {
+            let (mut before_fprs, mut after_fprs) = (before.recipients_list()?, after.recipients_list()?);
+            before_fprs.sort();
+            after_fprs.sort();
+            if before_fprs != after_fprs {
+                if after_fprs.is_empty() {
+                    bail!("Partition {} would not have any recipient afterwards.", after.url())
+                }
+                affected.push(after);
+            }
+        }
+        for partition in &affected {
+            if let Some(policy) = changed.find_key_policy(partition) {
+                policy.enforce(&mut ctx, &keys)?;
+            }
+        }
+        if let Ok(gpg_keys_dir) = self.find_gpg_keys_dir() {
+            let mut buf = Vec::new();
+            for key in &keys {
+                let (_, file_path) = export_key(&mut ctx, &gpg_keys_dir, key, &mut buf)?;
+                writeln!(
+                    output,
+                    "Exported public key for user {} to '{}'",
+                    KeyDisplay(key),
+                    file_path.display()
+                ).ok();
+            }
+        }
+        changed.serialize()?;
+        writeln!(output, "Wrote changed group '{}' to '{}'", name, changed.vault_path_for_display()).ok();
+
+        let has_multiple_partitions = !changed.partitions.is_empty();
+        for partition in affected {
+            partition.reencrypt(
+                &changed,
+                &mut ctx,
+                &changed.find_trust_model(partition),
+                changed.find_required_certifications(partition),
+                changed.find_keep_versions(partition),
+                changed
+                    .gpg_keys_dir_for_auto_import(partition)
+                    .as_ref()
+                    .map(PathBuf::as_path),
+                has_multiple_partitions,
+                output,
+                error,
+            )?;
+        }
+
+        if !add {
+            if let Ok(gpg_keys_dir) = changed.find_gpg_keys_dir() {
+                for fpr in &fprs {
+                    let mut still_used = false;
+                    for partition in once(&changed).chain(changed.partitions.iter()) {
+                        still_used |= is_member(&partition.recipients_list()?, fpr);
+                    }
+                    let key_path = gpg_keys_dir.join(fpr);
+                    if !still_used && key_path.is_file() {
+                        remove_file(&key_path)
+                            .context(format!("Failed to remove key file at '{}'", key_path.display()))?;
+                        writeln!(output, "Removed key file at '{}'", key_path.display()).ok();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_groups {
+    use super::*;
+
+    fn groups() -> Groups {
+        let mut groups = Groups::new();
+        groups.insert("admins".into(), vec!["A".into(), "@ops".into()]);
+        groups.insert("ops".into(), vec!["B".into(), "A".into()]);
+        groups.insert("loop".into(), vec!["@loop".into()]);
+        groups
+    }
+
+    fn expanded(entries: &[&str]) -> Result<Vec<String>, Error> {
+        let groups = groups();
+        let mut out = Vec::new();
+        expand(
+            &entries.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+            &|name| groups.get(name),
+            &mut Vec::new(),
+            &mut out,
+        ).map(|_| out)
+    }
+
+    #[test]
+    fn it_expands_nested_groups_without_duplicates() {
+        assert_eq!(expanded(&["C", "@admins", ""]).unwrap(), vec!["C", "A", "B"]);
+    }
+
+    #[test]
+    fn it_fails_on_unknown_or_cyclic_groups() {
+        assert!(expanded(&["@unknown"]).is_err());
+        assert!(expanded(&["@loop"]).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use gpgme;
+
+fn format_date(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).format("%Y-%m-%d").to_string()
+}
+
+/// What is known about the key of a recipient, for display or serialization.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct RecipientInfo {
+    pub fingerprint: String,
+    pub user_id: Option<String>,
+    pub algorithm: String,
+    pub length: usize,
+    pub created: Option<String>,
+    pub expires: Option<String>,
+    /// When the last usable encryption subkey expires, if all of them do.
+    pub encryption_expires: Option<String>,
+    pub revoked: bool,
+    pub expired: bool,
+    pub can_encrypt: bool,
+    pub groups: Vec<String>,
+    #[serde(skip)]
+    pub expires_at: Option<SystemTime>,
+    #[serde(skip)]
+    pub encryption_expires_at: Option<SystemTime>,
+}
+
+/// The time the last of the usable encryption subkeys of `key` expires, or None if one never expires or none exists.
+fn encryption_expiration_time(key: &gpgme::Key) -> Option<SystemTime> {
+    let mut latest = None;
+    for subkey in key.subkeys()
+        .filter(|k| k.can_encrypt() && !(k.is_revoked() || k.is_expired() || k.is_disabled() || k.is_invalid()))
+    {
+        match subkey.expiration_time() {
+            Some(expires) => latest = Some(latest.map_or(expires, |l: SystemTime| l.max(expires))),
+            None => return None,
+        }
+    }
+    latest
+}
+
+impl<'a> From<&'a gpgme::Key> for RecipientInfo {
+    fn from(key: &gpgme::Key) -> Self {
+        let primary = key.primary_key();
+        let encryption_expires_at = encryption_expiration_time(key);
+        RecipientInfo {
+            fingerprint: key.fingerprint().unwrap_or("[no fingerprint!]").to_owned(),
+            user_id: key.user_ids().next().and_then(|u| u.id().ok()).map(ToOwned::to_owned),
+            algorithm: primary
+                .as_ref()
+                .and_then(|k| k.algorithm_name().ok())
+                .unwrap_or_else(|| "unknown".into()),
+            length: primary.as_ref().map_or(0, |k| k.length()),
+            created: primary.as_ref().and_then(|k| k.creation_time()).map(format_date),
+            expires: primary.as_ref().and_then(|k| k.expiration_time()).map(format_date),
+            expires_at: primary.as_ref().and_then(|k| k.expiration_time()),
+            encryption_expires: encryption_expires_at.map(format_date),
+            encryption_expires_at,
+            groups: Vec::new(),
+            revoked: key.is_revoked(),
+            expired: key.is_expired(),
+            can_encrypt: key.subkeys()
+                .any(|k| k.can_encrypt() && !(k.is_revoked() || k.is_expired() || k.is_disabled() || k.is_invalid())),
+        }
+    }
+}
+
+impl fmt::Display for RecipientInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({})\t{}",
+            self.fingerprint,
+            self.user_id.as_ref().map(String::as_str).unwrap_or("[none]"),
+            self.algorithm
+        )?;
+        if !self.algorithm.ends_with(&self.length.to_string()) {
+            write!(f, "/{}", self.length)?;
+        }
+        if let Some(ref created) = self.created {
+            write!(f, ", created {}", created)?;
+        }
+        match self.expires {
+            Some(ref expires) if self.expired => write!(f, ", expired {}", expires)?,
+            Some(ref expires) => write!(f, ", expires {}", expires)?,
+            None => write!(f, ", never expires")?,
+        }
+        match (&self.encryption_expires, &self.expires) {
+            (&Some(ref encryption_expires), expires) if Some(encryption_expires) != expires.as_ref() => {
+                write!(f, ", encryption subkey expires {}", encryption_expires)?
+            }
+            _ => {}
+        }
+        if self.revoked {
+            write!(f, ", revoked")?;
+        }
+        if !self.can_encrypt {
+            write!(f, ", cannot encrypt")?;
+        }
+        if !self.groups.is_empty() {
+            write!(
+                f,
+                ", member of {}",
+                self.groups.iter().map(|g| format!("@{}", g)).collect::<Vec<_>>().join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The recipients of a single partition.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct PartitionRecipients {
+    pub partition: String,
+    pub recipients: Vec<RecipientInfo>,
+}
+
+#[cfg(test)]
+mod tests_info {
+    use super::*;
+
+    fn info() -> RecipientInfo {
+        RecipientInfo {
+            fingerprint: "905E53FE2FC0A500100AB80B056F92A52DF04D4E".into(),
+            user_id: Some("user c <c@example.com>".into()),
+            algorithm: "rsa2048".into(),
+            length: 2048,
+            created: Some("2018-03-04".into()),
+            expires: None,
+            encryption_expires: None,
+            revoked: false,
+            expired: false,
+            can_encrypt: true,
+            groups: Vec::new(),
+            expires_at: None,
+            encryption_expires_at: None,
+        }
+    }
+
+    #[test]
+    fn it_displays_healthy_keys_in_one_line() {
+        assert_eq!(
+            info().to_string(),
+            "905E53FE2FC0A500100AB80B056F92A52DF04D4E (user c <c@example.com>)\t\
+             rsa2048, created 2018-03-04, never expires"
+        );
+    }
+
+    #[test]
+    fn it_displays_problems_with_keys() {
+        let info = RecipientInfo {
+            algorithm: "unknown".into(),
+            expires: Some("2018-04-01".into()),
+            encryption_expires: Some("2018-03-01".into()),
+            expired: true,
+            revoked: true,
+            can_encrypt: false,
+            groups: vec!["admins".into(), "ops".into()],
+            ..info()
+        };
+        assert_eq!(
+            info.to_string(),
+            "905E53FE2FC0A500100AB80B056F92A52DF04D4E (user c <c@example.com>)\t\
+             unknown/2048, created 2018-03-04, expired 2018-04-01, encryption subkey expires 2018-03-01, \
+             revoked, cannot encrypt, \
+             member of @admins, @ops"
+        );
+    }
+}
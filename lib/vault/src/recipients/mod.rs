@@ -2,3 +2,19 @@ mod other;
 mod add;
 mod remove;
 mod util;
+mod info;
+mod check;
+mod refresh;
+mod replace;
+mod groups;
+mod file;
+mod requests;
+mod signature;
+mod certify;
+mod policy;
+
+pub use self::info::{PartitionRecipients, RecipientInfo};
+pub use self::check::warn_about_expiring_keys;
+pub use self::groups::Groups;
+pub use self::file::RecipientsFile;
+pub use self::policy::KeyPolicy;
@@ -1,7 +1,12 @@
 use failure::Error;
 use std::io::Write;
 use base::Vault;
-use util::{FingerprintUserId, UserIdFingerprint};
+use util::UserIdFingerprint;
+use super::info::{PartitionRecipients, RecipientInfo};
+use gpgme;
+use serde_json;
+use serde_yaml;
+use tools::process::OutputMode;
 use util::new_context;
 use util::extract_at_least_one_secret_key;
 use util::export_key;
@@ -22,28 +27,63 @@ impl Vault {
         Ok(())
     }
 
-    pub fn print_recipients(&self, output: &mut Write, error: &mut Write) -> Result<(), Error> {
+    fn partition_recipients(
+        &self,
+        ctx: &mut gpgme::Context,
+        partition: &Vault,
+        error: &mut Write,
+    ) -> Result<PartitionRecipients, Error> {
+        let keys = partition.recipient_keys(
+            ctx,
+            self.gpg_keys_dir_for_auto_import(partition)
+                .as_ref()
+                .map(PathBuf::as_path),
+            error,
+        )?;
+        Ok(PartitionRecipients {
+            partition: partition.url(),
+            recipients: keys.iter()
+                .map(|key| {
+                    let mut info = RecipientInfo::from(key);
+                    info.groups = partition.groups_of(&info.fingerprint);
+                    info
+                })
+                .collect(),
+        })
+    }
+
+    pub fn recipients_info(&self, error: &mut Write) -> Result<Vec<PartitionRecipients>, Error> {
         let mut ctx = new_context()?;
-        if self.partitions.is_empty() {
-            let keys_dir_for_auto_import = if self.auto_import.clone().unwrap_or(false) {
-                self.gpg_keys_dir().ok()
-            } else {
-                None
-            };
-            for key in self.recipient_keys(&mut ctx, keys_dir_for_auto_import.as_ref().map(PathBuf::as_path), error)? {
-                writeln!(output, "{}", FingerprintUserId(&key)).ok();
+        once(self)
+            .chain(self.partitions.iter())
+            .map(|partition| self.partition_recipients(&mut ctx, partition, error))
+            .collect()
+    }
+
+    pub fn print_recipients(
+        &self,
+        mode: Option<OutputMode>,
+        output: &mut Write,
+        error: &mut Write,
+    ) -> Result<(), Error> {
+        match mode {
+            Some(OutputMode::Json) => {
+                serde_json::to_writer_pretty(&mut *output, &self.recipients_info(error)?)?;
+                writeln!(output)?;
+            }
+            Some(OutputMode::Yaml) => {
+                serde_yaml::to_writer(&mut *output, &self.recipients_info(error)?)?;
+                writeln!(output)?;
             }
-        } else {
-            for partition in once(self).chain(self.partitions.iter()) {
-                writeln!(output, "{}", partition.url())?;
-                for key in partition.recipient_keys(
-                    &mut ctx,
-                    self.gpg_keys_dir_for_auto_import(partition)
-                        .as_ref()
-                        .map(PathBuf::as_path),
-                    error,
-                )? {
-                    writeln!(output, "{}", FingerprintUserId(&key)).ok();
+            None => {
+                let mut ctx = new_context()?;
+                for partition in once(self).chain(self.partitions.iter()) {
+                    if !self.partitions.is_empty() {
+                        writeln!(output, "{}", partition.url())?;
+                    }
+                    for recipient in self.partition_recipients(&mut ctx, partition, error)?.recipients {
+                        writeln!(output, "{}", recipient).ok();
+                    }
                 }
             }
         }
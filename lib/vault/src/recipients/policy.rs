@@ -0,0 +1,298 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use base::Vault;
+use chrono::{DateTime, Utc};
+use failure::{Error, ResultExt};
+use gpgme::{self, Key};
+use serde_yaml;
+use util::UserIdFingerprint;
+
+const SECONDS_PER_YEAR: u64 = 31_557_600;
+const HASH_ALGORITHM_SHA1: u8 = 2;
+
+/// Requirements for the keys of recipients, as configured in the `key_policy` section of the vault description file.
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone, Hash)]
+pub struct KeyPolicy {
+    /// Algorithms as named by gpg, like 'rsa4096' or 'ed25519'. An entry allows all names starting with it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub algorithms: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_rsa_bits: Option<usize>,
+    /// Keys and their encryption subkeys must expire within this amount of years.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_expiry_years: Option<u64>,
+    #[serde(default)]
+    pub forbid_sha1_self_signatures: bool,
+}
+
+fn read_u32(bytes: &[u8]) -> Option<usize> {
+    if bytes.len() < 4 {
+        return None;
+    }
+    Some(((bytes[0] as usize) << 24) | ((bytes[1] as usize) << 16) | ((bytes[2] as usize) << 8) | bytes[3] as usize)
+}
+
+/// Split binary OpenPGP data into (tag, body) pairs. Stops at anything it can't parse.
+fn packets(mut data: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut packets = Vec::new();
+    while let Some(&header) = data.first() {
+        if header & 0x80 == 0 {
+            break;
+        }
+        let (tag, offset, len) = if header & 0x40 != 0 {
+            match data.get(1).cloned() {
+                Some(l @ 0...191) => (header & 0x3f, 2, l as usize),
+                Some(l @ 192...223) => match data.get(2) {
+                    Some(&l2) => (header & 0x3f, 3, ((l as usize - 192) << 8) + l2 as usize + 192),
+                    None => break,
+                },
+                Some(255) => match read_u32(&data[2..]) {
+                    Some(l) => (header & 0x3f, 6, l),
+                    None => break,
+                },
+                _ => break,
+            }
+        } else {
+            let tag = (header >> 2) & 0x0f;
+            match header & 0x03 {
+                0 => match data.get(1) {
+                    Some(&l) => (tag, 2, l as usize),
+                    None => break,
+                },
+                1 => match (data.get(1), data.get(2)) {
+                    (Some(&l1), Some(&l2)) => (tag, 3, ((l1 as usize) << 8) | l2 as usize),
+                    _ => break,
+                },
+                2 => match read_u32(&data[1..]) {
+                    Some(l) => (tag, 5, l),
+                    None => break,
+                },
+                _ => (tag, 1, data.len() - 1),
+            }
+        };
+        if data.len() < offset + len {
+            break;
+        }
+        packets.push((tag, &data[offset..offset + len]));
+        data = &data[offset + len..];
+    }
+    packets
+}
+
+/// The issuer key id of a v4 signature, found in the issuer or issuer fingerprint subpackets.
+fn issuer_of(mut subpackets: &[u8]) -> Option<&[u8]> {
+    while let Some(&first) = subpackets.first() {
+        let (offset, len) = match first {
+            0...191 => (1, first as usize),
+            192...254 => (2, ((first as usize - 192) << 8) + *subpackets.get(1)? as usize + 192),
+            _ => (5, read_u32(&subpackets[1..])?),
+        };
+        if len == 0 || subpackets.len() < offset + len {
+            return None;
+        }
+        let body = &subpackets[offset + 1..offset + len];
+        match subpackets[offset] & 0x7f {
+            16 if body.len() == 8 => return Some(body),
+            33 if body.len() == 21 => return Some(&body[13..]),
+            _ => {}
+        }
+        subpackets = &subpackets[offset + len..];
+    }
+    None
+}
+
+/// The hash algorithms of all certifications made by `key_id` in the binary OpenPGP `data` of a public key.
+fn self_signature_hash_algorithms(data: &[u8], key_id: &[u8]) -> Vec<u8> {
+    let mut algorithms = Vec::new();
+    for (tag, body) in packets(data) {
+        if tag != 2 || body.len() < 17 {
+            continue;
+        }
+        let (sig_type, hash_algorithm, issuer) = match body[0] {
+            3 => (body[2], body[16], Some(&body[7..15])),
+            4 => {
+                let hashed_len = ((body[4] as usize) << 8) | body[5] as usize;
+                let hashed = body.get(6..6 + hashed_len).unwrap_or(&[]);
+                let unhashed = body.get(8 + hashed_len..).unwrap_or(&[]);
+                (body[1], body[3], issuer_of(hashed).or_else(|| issuer_of(unhashed)))
+            }
+            _ => continue,
+        };
+        if sig_type >= 0x10 && sig_type <= 0x13 && issuer == Some(key_id) {
+            algorithms.push(hash_algorithm);
+        }
+    }
+    algorithms
+}
+
+fn key_id_of(fingerprint: &str) -> Vec<u8> {
+    let fingerprint = fingerprint.as_bytes();
+    fingerprint[fingerprint.len().saturating_sub(16)..]
+        .chunks(2)
+        .filter_map(|c| ::std::str::from_utf8(c).ok())
+        .filter_map(|c| u8::from_str_radix(c, 16).ok())
+        .collect()
+}
+
+fn format_date(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).format("%Y-%m-%d").to_string()
+}
+
+/// The reason why a key expiring at `expires` does not expire until `latest`, which is `years` from now.
+fn expiry_violation(expires: Option<SystemTime>, latest: SystemTime, years: u64) -> Option<String> {
+    match expires {
+        None => Some(format!("never expires, but must expire within {} year(s)", years)),
+        Some(expires) if expires > latest => Some(format!(
+            "expires {}, which is more than {} year(s) from now",
+            format_date(expires),
+            years
+        )),
+        Some(_) => None,
+    }
+}
+
+impl KeyPolicy {
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let content = fs::read(path).context(format!("Could not read key policy at '{}'.", path.display()))?;
+        Ok(serde_yaml::from_slice(&content).context(format!("Could not parse key policy at '{}'.", path.display()))?)
+    }
+
+    /// All the ways `key` violates this policy, judged at `now`.
+    pub fn violations(&self, ctx: &mut gpgme::Context, key: &Key, now: SystemTime) -> Result<Vec<String>, Error> {
+        let mut violations = Vec::new();
+        for subkey in key.subkeys()
+            .filter(|k| !(k.is_revoked() || k.is_expired() || k.is_disabled() || k.is_invalid()))
+        {
+            let name = subkey.algorithm_name().unwrap_or_else(|_| "unknown".into());
+            if let Some(ref algorithms) = self.algorithms {
+                if !algorithms.iter().any(|a| name.starts_with(a.as_str())) {
+                    violations.push(format!("uses algorithm {}, which is not allowed", name));
+                }
+            }
+            if let Some(min_rsa_bits) = self.min_rsa_bits {
+                if name.starts_with("rsa") && subkey.length() < min_rsa_bits {
+                    violations.push(format!(
+                        "has a {} bit RSA key, but at least {} bits are required",
+                        subkey.length(),
+                        min_rsa_bits
+                    ));
+                }
+            }
+        }
+        if let Some(years) = self.max_expiry_years {
+            let latest = now + Duration::from_secs(years * SECONDS_PER_YEAR);
+            let expires = key.primary_key().and_then(|k| k.expiration_time());
+            violations.extend(expiry_violation(expires, latest, years));
+            for subkey in key.subkeys().skip(1).filter(|k| {
+                k.can_encrypt() && !(k.is_revoked() || k.is_expired() || k.is_disabled() || k.is_invalid())
+            }) {
+                if let Some(violation) = expiry_violation(subkey.expiration_time(), latest, years) {
+                    violations.push(format!(
+                        "has encryption subkey {} which {}",
+                        subkey.id().unwrap_or("unknown"),
+                        violation
+                    ));
+                }
+            }
+        }
+        if self.forbid_sha1_self_signatures {
+            let fingerprint = key.fingerprint().unwrap_or("");
+            let previous_armor = ctx.armor();
+            ctx.set_armor(false);
+            let mut data = Vec::new();
+            let res = ctx.export_keys(Some(key), gpgme::ExportMode::empty(), &mut data);
+            ctx.set_armor(previous_armor);
+            res.context(format!("Could not export key {} to inspect its self-signatures.", fingerprint))?;
+            let algorithms = self_signature_hash_algorithms(&data, &key_id_of(fingerprint));
+            if !algorithms.is_empty() && algorithms.iter().all(|a| *a == HASH_ALGORITHM_SHA1) {
+                violations.push("has only SHA-1 self-signatures".into());
+            }
+        }
+        Ok(violations)
+    }
+
+    /// Fail if any of `keys` violates this policy, listing all violations.
+    pub fn enforce(&self, ctx: &mut gpgme::Context, keys: &[Key]) -> Result<(), Error> {
+        let now = SystemTime::now();
+        let mut offenders = Vec::new();
+        for key in keys {
+            let violations = self.violations(ctx, key, now)?;
+            if !violations.is_empty() {
+                offenders.push(format!("{}: {}", UserIdFingerprint(key), violations.join(", ")));
+            }
+        }
+        if !offenders.is_empty() {
+            bail!(
+                "The following key(s) violate the key policy of the vault:\n{}",
+                offenders.join("\n")
+            )
+        }
+        Ok(())
+    }
+}
+
+impl Vault {
+    pub fn find_key_policy<'a>(&'a self, partition: &'a Vault) -> Option<&'a KeyPolicy> {
+        partition.key_policy.as_ref().or_else(|| self.key_policy.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests_policy {
+    use super::*;
+
+    fn new_format_packet(tag: u8, body: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0xc0 | tag, body.len() as u8];
+        packet.extend_from_slice(body);
+        packet
+    }
+
+    fn v4_certification(hash_algorithm: u8, issuer: &[u8]) -> Vec<u8> {
+        let mut subpackets = vec![9, 16];
+        subpackets.extend_from_slice(issuer);
+        let mut body = vec![4, 0x13, 1, hash_algorithm, 0, subpackets.len() as u8];
+        body.extend_from_slice(&subpackets);
+        body.extend_from_slice(&[0, 0, 0xab, 0xcd]);
+        new_format_packet(2, &body)
+    }
+
+    #[test]
+    fn it_finds_hash_algorithms_of_self_signatures_only() {
+        let own = key_id_of("0123456789ABCDEF0011223344556677DEADBEEF");
+        let other = [1u8; 8];
+        let mut data = new_format_packet(13, b"tester <tester@example.com>");
+        data.extend(v4_certification(HASH_ALGORITHM_SHA1, &own));
+        data.extend(v4_certification(8, &other));
+        data.extend(v4_certification(10, &own));
+        assert_eq!(self_signature_hash_algorithms(&data, &own), vec![HASH_ALGORITHM_SHA1, 10]);
+    }
+
+    #[test]
+    fn it_stops_at_truncated_packets() {
+        let own = key_id_of("DEADBEEF00112233");
+        let mut data = v4_certification(HASH_ALGORITHM_SHA1, &own);
+        data.truncate(10);
+        assert!(self_signature_hash_algorithms(&data, &own).is_empty());
+    }
+
+    #[test]
+    fn it_requires_keys_to_expire_in_time() {
+        let now = SystemTime::now();
+        let latest = now + Duration::from_secs(SECONDS_PER_YEAR);
+        assert!(expiry_violation(Some(now), latest, 1).is_none());
+        assert!(expiry_violation(Some(latest + Duration::from_secs(1)), latest, 1).is_some());
+        assert_eq!(
+            expiry_violation(None, latest, 1),
+            Some("never expires, but must expire within 1 year(s)".into())
+        );
+    }
+
+    #[test]
+    fn it_reads_a_policy_without_all_fields() {
+        let policy: KeyPolicy = serde_yaml::from_str("min_rsa_bits: 3072").unwrap();
+        assert_eq!(policy.min_rsa_bits, Some(3072));
+        assert!(!policy.forbid_sha1_self_signatures);
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Write;
+use std::iter::once;
+use std::path::Path;
+
+use base::Vault;
+use failure::{Error, ResultExt};
+use gpgme;
+use util::{export_key, fingerprint_of, new_context, UserIdFingerprint};
+use super::info::RecipientInfo;
+
+/// The fingerprints of all keys in `keys`, without importing them.
+fn fingerprints_in(ctx: &mut gpgme::Context, keys: &[u8]) -> Result<Vec<String>, Error> {
+    ctx.read_keys(keys)?
+        .map(|key| key.map_err(Into::into).and_then(|key| fingerprint_of(&key)))
+        .collect()
+}
+
+impl Vault {
+    /// Export the public key of each recipient of all partitions from the gpg keyring into the gpg keys
+    /// directory if it changed, after importing updated keys from `from` if given.
+    /// Fails if `from` contains keys of someone who isn't a recipient, before importing any of them,
+    /// or if any key file does.
+    pub fn refresh_recipients(&self, from: Option<&Path>, output: &mut Write) -> Result<(), Error> {
+        let gpg_keys_dir = self.find_gpg_keys_dir()?;
+        let mut ctx = new_context()?;
+        let mut fingerprints = BTreeSet::new();
+        for partition in once(self).chain(self.partitions.iter()) {
+            fingerprints.extend(partition.recipients_list()?);
+        }
+
+        if let Some(from) = from {
+            let keys = fs::read(from).context(format!("Could not read keys from '{}'.", from.display()))?;
+            let foreign: Vec<_> = fingerprints_in(&mut ctx, &keys)
+                .context(format!("Could not read keys from '{}'.", from.display()))?
+                .into_iter()
+                .filter(|fpr| !fingerprints.iter().any(|r| fpr.ends_with(&r.to_uppercase())))
+                .collect();
+            if !foreign.is_empty() {
+                bail!(
+                    "'{}' contains keys of the following non-recipients, which might mean somebody is trying \
+                     to sneak in their key. Nothing was imported.\n{}",
+                    from.display(),
+                    foreign.join("\n")
+                )
+            }
+            let result = ctx.import(keys)
+                .context(format!("Could not import keys from '{}'.", from.display()))?;
+            writeln!(
+                output,
+                "Imported {} of {} key(s) from '{}', {} were unchanged.",
+                result.considered() - result.unchanged(),
+                result.considered(),
+                from.display(),
+                result.unchanged()
+            ).ok();
+        }
+
+        let mut buf = Vec::new();
+        let mut num_unchanged = 0;
+        let mut missing = Vec::new();
+        for fpr in fingerprints {
+            let key_path = gpg_keys_dir.join(&fpr);
+            let previous = fs::read(&key_path).ok();
+            if previous.is_some() {
+                // Merge the exported key first, so we never export a key that is older than the file
+                let mut zero = Vec::new();
+                self.import_keys(&mut ctx, &gpg_keys_dir, &[fpr.clone()], &mut zero)?;
+            }
+            let key = match ctx.find_key(fpr.as_str()) {
+                Ok(key) => key,
+                Err(_) => {
+                    missing.push(fpr);
+                    continue;
+                }
+            };
+            let (_, key_path) = export_key(&mut ctx, &gpg_keys_dir, &key, &mut buf)?;
+            let exported = fs::read(&key_path).context(format!("Could not read key at '{}'.", key_path.display()))?;
+            let expiry = match RecipientInfo::from(&key).expires {
+                Some(expires) => format!("expires {}", expires),
+                None => "never expires".to_owned(),
+            };
+            match previous {
+                Some(ref previous) if *previous == exported => num_unchanged += 1,
+                Some(_) => writeln!(
+                    output,
+                    "Updated public key of {} at '{}', it {}.",
+                    UserIdFingerprint(&key),
+                    key_path.display(),
+                    expiry
+                )?,
+                None => writeln!(
+                    output,
+                    "Exported public key of {} to '{}', it {}.",
+                    UserIdFingerprint(&key),
+                    key_path.display(),
+                    expiry
+                )?,
+            }
+        }
+        if num_unchanged > 0 {
+            writeln!(output, "{} public key(s) were up to date.", num_unchanged).ok();
+        }
+        if !missing.is_empty() {
+            bail!(
+                "The following recipient(s) could not be found in the gpg keyring:\n{}",
+                missing.join("\n")
+            )
+        }
+        Ok(())
+    }
+}
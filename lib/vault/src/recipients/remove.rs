@@ -14,6 +14,7 @@ impl Vault {
         gpg_key_ids: &[String],
         partitions: &[String],
         output: &mut Write,
+        error: &mut Write,
     ) -> Result<(), Error> {
         let mut ctx = new_context()?;
         let partitions = self.partitions_by_name_or_path(partitions)?;
@@ -62,7 +63,27 @@ impl Vault {
                 (keys_and_fprs_to_remove, recipient_keys_and_fprs)
             };
 
+            let mut entries = partition.recipients_entries()?;
             for (key, fpr) in keys_and_fprs_to_remove {
+                let groups = partition.groups_of(&fpr);
+                if !groups.is_empty() {
+                    bail!(
+                        "Cannot remove user {} from '{}' as it is a recipient through group(s) {}. \
+                         Use 'recipients group remove' instead.",
+                        UserIdFingerprint(key),
+                        partition.recipients_path().display(),
+                        groups.iter().map(|g| format!("@{}", g)).join(", ")
+                    )
+                }
+                let num_entries = entries.len();
+                entries.retain(|e| !fpr.ends_with(&e.to_uppercase()));
+                if entries.len() == num_entries {
+                    bail!(
+                        "Could not find an entry for user {} in '{}'.",
+                        UserIdFingerprint(key),
+                        partition.recipients_path().display()
+                    )
+                }
                 remaining_recipients_fprs.retain(|rfpr| rfpr != &fpr);
                 if remaining_recipients_fprs.is_empty() {
                     bail!(
@@ -91,7 +112,7 @@ impl Vault {
                 }
             }
 
-            let written_file = partition.write_recipients_list(&mut remaining_recipients_fprs)?;
+            let written_file = partition.write_recipients_list(&mut entries)?;
             writeln!(
                 output,
                 "Wrote changed recipients to file at '{}'",
@@ -99,11 +120,15 @@ impl Vault {
             ).ok();
 
             partition.reencrypt(
+                self,
                 &mut ctx,
                 &self.find_trust_model(partition),
+                self.find_required_certifications(partition),
+                self.find_keep_versions(partition),
                 gpg_keys_dir.as_ref().map(PathBuf::as_path),
                 has_multiple_partitions,
                 output,
+                error,
             )?;
         }
         Ok(())
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use std::fs::remove_file;
+use std::io::Write;
+use std::iter::once;
+use std::path::PathBuf;
+use std::slice;
+
+use base::Vault;
+use failure::{Error, ResultExt};
+use gpgme;
+use util::{export_key, fingerprint_of, new_context, KeyDisplay, KeylistDisplay};
+
+impl Vault {
+    /// The fingerprint of the recipient `id` as it is stored in at least one recipients list.
+    fn recipient_fingerprint(&self, ctx: &mut gpgme::Context, id: &str) -> Result<String, Error> {
+        let id_upper = id.to_uppercase();
+        for partition in once(self).chain(self.partitions.iter()) {
+            if let Some(fpr) = partition
+                .recipients_list()?
+                .into_iter()
+                .find(|fpr| fpr.to_uppercase().ends_with(&id_upper))
+            {
+                return Ok(fpr);
+            }
+        }
+        let key = ctx.find_keys(Some(id))?
+            .filter_map(Result::ok)
+            .next()
+            .ok_or_else(|| {
+                format_err!(
+                    "Could not find recipient '{}' in any recipients list or the gpg keyring.",
+                    id
+                )
+            })?;
+        fingerprint_of(&key)
+    }
+
+    /// Replace the recipient `old_id` by `new_id` in all recipients files and groups having it as member,
+    /// re-encrypting each of them once.
+    pub fn replace_recipient(
+        &self,
+        old_id: &str,
+        new_id: &str,
+        output: &mut Write,
+        error: &mut Write,
+    ) -> Result<(), Error> {
+        let mut ctx = new_context()?;
+        let has_multiple_partitions = !self.partitions.is_empty();
+        let old_fpr = self.recipient_fingerprint(&mut ctx, old_id)?;
+        let new_key = {
+            let mut keys = self.keys_by_ids(
+                &mut ctx,
+                &[new_id.to_owned()],
+                "user-id",
+                self.gpg_keys_dir_for_auto_import(self)
+                    .as_ref()
+                    .map(PathBuf::as_path),
+                output,
+            )?;
+            if keys.len() != 1 {
+                bail!(
+                    "Expected exactly one key for '{}', but found {} ({}).",
+                    new_id,
+                    keys.len(),
+                    KeylistDisplay(&keys)
+                )
+            }
+            keys.pop().expect("one key")
+        };
+        let new_fpr = fingerprint_of(&new_key)?;
+        if new_fpr == old_fpr {
+            bail!("The old and the new recipient are the same key {}.", KeyDisplay(&new_key))
+        }
+
+        let affected: Vec<usize> = once(self)
+            .chain(self.partitions.iter())
+            .filter(|p| {
+                p.recipients_list()
+                    .map(|fprs| fprs.iter().any(|fpr| *fpr == old_fpr))
+                    .unwrap_or(false)
+            })
+            .map(|p| p.index)
+            .collect();
+        if affected.is_empty() {
+            bail!("Recipient {} is not in any recipients list.", old_fpr)
+        }
+        for partition in once(self)
+            .chain(self.partitions.iter())
+            .filter(|p| affected.contains(&p.index))
+        {
+            partition.verify_recipients_signature(&mut ctx)?;
+            if let Some(policy) = self.find_key_policy(partition) {
+                policy.enforce(&mut ctx, slice::from_ref(&new_key))?;
+            }
+        }
+
+        let gpg_keys_dir = self.find_gpg_keys_dir().ok();
+        if let Some(gpg_keys_dir) = gpg_keys_dir.as_ref() {
+            let mut buf = Vec::new();
+            let (_, file_path) = export_key(&mut ctx, gpg_keys_dir, &new_key, &mut buf)?;
+            writeln!(
+                output,
+                "Exported public key for user {} to '{}'",
+                KeyDisplay(&new_key),
+                file_path.display()
+            ).ok();
+        }
+
+        let mut changed = self.clone();
+        let mut changed_groups = false;
+        {
+            let mut replace_in_groups = |vault: &mut Vault| {
+                for (name, members) in vault.groups.iter_mut().flat_map(|g| g.iter_mut()) {
+                    if members.iter().any(|m| *m == old_fpr) {
+                        members.retain(|m| *m != old_fpr);
+                        if !members.iter().any(|m| *m == new_fpr) {
+                            members.push(new_fpr.clone());
+                        }
+                        changed_groups = true;
+                        writeln!(output, "Replaced recipient {} by {} in group '{}'", old_fpr, new_fpr, name).ok();
+                    }
+                }
+            };
+            replace_in_groups(&mut changed);
+            for partition in &mut changed.partitions {
+                replace_in_groups(partition);
+            }
+        }
+        if changed_groups {
+            changed.share_groups();
+            changed.serialize()?;
+        }
+
+        for partition in once(&changed)
+            .chain(changed.partitions.iter())
+            .filter(|p| affected.contains(&p.index))
+        {
+            let mut entries = partition.recipients_entries()?;
+            if entries.iter().any(|e| *e == old_fpr) {
+                let mut comments = BTreeMap::new();
+                if let Some(comment) = partition.recipient_comment(&old_fpr)? {
+                    comments.insert(new_fpr.clone(), comment);
+                }
+                entries.retain(|e| *e != old_fpr);
+                entries.push(new_fpr.clone());
+                let written_file = partition.write_recipients_file(&mut entries, &comments)?;
+                writeln!(
+                    output,
+                    "Replaced recipient {} by {} in '{}'",
+                    old_fpr,
+                    KeyDisplay(&new_key),
+                    written_file.display()
+                ).ok();
+            }
+            partition.reencrypt(
+                &changed,
+                &mut ctx,
+                &changed.find_trust_model(partition),
+                changed.find_required_certifications(partition),
+                changed.find_keep_versions(partition),
+                changed
+                    .gpg_keys_dir_for_auto_import(partition)
+                    .as_ref()
+                    .map(PathBuf::as_path),
+                has_multiple_partitions,
+                output,
+                error,
+            )?;
+        }
+
+        if let Some(gpg_keys_dir) = gpg_keys_dir {
+            let old_key_path = gpg_keys_dir.join(&old_fpr);
+            if old_key_path.is_file() {
+                remove_file(&old_key_path)
+                    .context(format!("Failed to remove key file at '{}'", old_key_path.display()))?;
+                writeln!(output, "Removed key file at '{}'", old_key_path.display()).ok();
+            }
+        }
+        Ok(())
+    }
+}
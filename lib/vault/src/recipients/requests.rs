@@ -0,0 +1,222 @@
+use std::fs::{self, create_dir_all, remove_file};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use base::Vault;
+use failure::{Error, ResultExt};
+use gpgme;
+use spec::SigningMode;
+use util::{export_key, extract_at_least_one_secret_key, new_context, write_at, UserIdFingerprint};
+
+/// The directory within the gpg keys directory holding the public keys of those requesting access.
+pub const PENDING_DIR: &str = "pending";
+const NOTE_SUFFIX: &str = ".note.asc";
+
+fn note_path_of(key_path: &Path) -> PathBuf {
+    let mut file_name = key_path.file_name().expect("file name").to_owned();
+    file_name.push(NOTE_SUFFIX);
+    key_path.with_file_name(file_name)
+}
+
+/// The text of the note at `note_path`, and whether it is signed by the key with fingerprint `fpr`.
+fn verified_note(ctx: &mut gpgme::Context, note_path: &Path, fpr: &str) -> Result<(Vec<u8>, bool), Error> {
+    let signed = fs::read(note_path).context(format!("Could not read note at '{}'.", note_path.display()))?;
+    let mut note = Vec::new();
+    let signed_by_requester = ctx.verify_opaque(&signed, &mut note)
+        .map(|result| {
+            result
+                .signatures()
+                .any(|s| s.status().is_ok() && s.fingerprint().map(|f| f == fpr).unwrap_or(false))
+        })
+        .unwrap_or(false);
+    Ok((note, signed_by_requester))
+}
+
+/// The first line of a note, used as comment in the recipients file.
+fn first_line(text: &[u8]) -> String {
+    String::from_utf8_lossy(text).lines().next().unwrap_or("").trim().to_owned()
+}
+
+impl Vault {
+    fn pending_dir(&self) -> Result<PathBuf, Error> {
+        Ok(self.find_gpg_keys_dir()
+            .context("Access requests require a vault that has the `gpg-keys` directory configured")?
+            .join(PENDING_DIR))
+    }
+
+    /// The fingerprints of all pending requests, along with the path to their public key.
+    pub fn pending_requests(&self) -> Result<Vec<(String, PathBuf)>, Error> {
+        let dir = self.pending_dir()?;
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut requests: Vec<_> = fs::read_dir(&dir)
+            .context(format!("Could not read pending requests at '{}'.", dir.display()))?
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .filter_map(|p| {
+                let name = p.file_name()?.to_str()?.to_owned();
+                if name.ends_with(NOTE_SUFFIX) {
+                    None
+                } else {
+                    Some((name, p))
+                }
+            })
+            .collect();
+        requests.sort();
+        Ok(requests)
+    }
+
+    /// Export the public key of the caller into the pending directory, along with `note` signed by it.
+    pub fn request_access(&self, gpg_key_ids: &[String], note: Option<&str>, output: &mut Write) -> Result<(), Error> {
+        let dir = self.pending_dir()?;
+        create_dir_all(&dir).context(format!("Could not create directory at '{}'.", dir.display()))?;
+        let mut ctx = new_context()?;
+        let keys = extract_at_least_one_secret_key(&mut ctx, gpg_key_ids)?;
+        let mut buf = Vec::new();
+        for key in keys {
+            let (_, key_path) = export_key(&mut ctx, &dir, &key, &mut buf)?;
+            writeln!(
+                output,
+                "Requested access for {} with public key at '{}'",
+                UserIdFingerprint(&key),
+                key_path.display()
+            ).ok();
+            if let Some(note) = note {
+                ctx.clear_signers();
+                ctx.add_signer(&key)?;
+                let mut signed = Vec::new();
+                ctx.sign_clear(note, &mut signed)
+                    .context("Could not sign the note of the access request.")?;
+                ctx.clear_signers();
+                let note_path = note_path_of(&key_path);
+                write_at(&note_path)
+                    .and_then(|mut f| f.write_all(&signed))
+                    .context(format!("Could not write note to '{}'.", note_path.display()))?;
+            }
+        }
+        writeln!(
+            output,
+            "Please commit and share these files, and ask an existing recipient to run 'recipients approve'."
+        ).ok();
+        Ok(())
+    }
+
+    /// List all pending requests, importing their keys to show who they belong to and to verify their notes.
+    pub fn print_pending(&self, output: &mut Write) -> Result<(), Error> {
+        let dir = self.pending_dir()?;
+        let mut ctx = new_context()?;
+        for (fpr, key_path) in self.pending_requests()? {
+            let mut zero = Vec::new();
+            if let Err(err) = self.import_keys(&mut ctx, &dir, &[fpr.clone()], &mut zero) {
+                writeln!(output, "{}\n    WARNING: The request cannot be approved: {}", fpr, err)?;
+                continue;
+            }
+            match ctx.find_key(fpr.as_str()) {
+                Ok(key) => writeln!(output, "{}", UserIdFingerprint(&key))?,
+                Err(_) => writeln!(output, "{} (key file does not contain this key)", fpr)?,
+            }
+            let note_path = note_path_of(&key_path);
+            if note_path.is_file() {
+                match verified_note(&mut ctx, &note_path, &fpr) {
+                    Ok((note, signed_by_requester)) => {
+                        for line in String::from_utf8_lossy(&note).lines() {
+                            writeln!(output, "    {}", line)?;
+                        }
+                        if !signed_by_requester {
+                            writeln!(output, "    WARNING: The note above is not signed by the requesting key.")?;
+                        }
+                    }
+                    Err(err) => writeln!(output, "    WARNING: The note cannot be shown: {}", err)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Certify the key of the pending request `id` with `signing_key_id` and add it as recipient, then remove
+    /// the request. The first line of its note becomes the comment of the recipient if the requester signed it.
+    pub fn approve_request(
+        &self,
+        id: &str,
+        signing_key_id: Option<&str>,
+        partitions: &[String],
+        output: &mut Write,
+        error: &mut Write,
+    ) -> Result<(), Error> {
+        let id_upper = id.to_uppercase();
+        let mut matches: Vec<_> = self.pending_requests()?
+            .into_iter()
+            .filter(|&(ref fpr, _)| fpr.to_uppercase().ends_with(&id_upper))
+            .collect();
+        let (fpr, key_path) = match matches.len() {
+            0 => bail!("There is no pending request for '{}'.", id),
+            1 => matches.pop().expect("one match"),
+            n => bail!("'{}' matches {} pending requests, please use the full fingerprint.", id, n),
+        };
+        let gpg_keys_dir = self.find_gpg_keys_dir()?;
+        let mut ctx = new_context()?;
+        self.import_keys(&mut ctx, &self.pending_dir()?, &[fpr.clone()], output)?;
+        let note_path = note_path_of(&key_path);
+        let comment = if note_path.is_file() {
+            match verified_note(&mut ctx, &note_path, &fpr)? {
+                (note, true) => Some(first_line(&note)).filter(|c| !c.is_empty()),
+                (_, false) => {
+                    writeln!(
+                        output,
+                        "Ignoring the note at '{}' as it is not signed by the requesting key.",
+                        note_path.display()
+                    ).ok();
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Adding signed recipients imports and certifies their key from the gpg keys directory.
+        let shared_key_path = gpg_keys_dir.join(&fpr);
+        let had_shared_key = shared_key_path.is_file();
+        if !had_shared_key {
+            fs::copy(&key_path, &shared_key_path).context(format!(
+                "Could not copy key at '{}' to '{}'.",
+                key_path.display(),
+                shared_key_path.display()
+            ))?;
+        }
+        let res = self.add_recipients(
+            &[fpr.clone()],
+            SigningMode::Public,
+            signing_key_id,
+            partitions,
+            comment.as_ref().map(String::as_str),
+            output,
+            error,
+        );
+        if res.is_err() && !had_shared_key {
+            remove_file(&shared_key_path).ok();
+        }
+        res?;
+        for path in &[key_path, note_path] {
+            if path.is_file() {
+                remove_file(path).context(format!("Could not remove '{}'.", path.display()))?;
+            }
+        }
+        writeln!(output, "Approved access request of {}", fpr).ok();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_requests {
+    use super::*;
+
+    #[test]
+    fn it_keeps_notes_next_to_the_key() {
+        assert_eq!(
+            note_path_of(Path::new("keys/pending/ABCD")),
+            Path::new("keys/pending/ABCD.note.asc")
+        );
+    }
+}
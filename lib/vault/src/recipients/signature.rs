@@ -0,0 +1,302 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Component, PathBuf};
+
+use base::Vault;
+use failure::{Error, ResultExt};
+use gpgme::{self, Validity};
+use util::{fingerprint_of, new_context, write_at, UserIdFingerprint};
+
+pub const SIGNATURE_SUFFIX: &str = ".sig";
+const TRUSTED_RECIPIENTS_DIR: &str = "sheesy-trusted-recipients";
+
+/// The data that is signed: all fingerprints of the recipients, including those of groups, one per line.
+/// This way comments may change freely, while changes to groups are detected as well.
+fn signed_content(fingerprints: &[String]) -> Vec<u8> {
+    let mut fingerprints = fingerprints.to_owned();
+    fingerprints.sort();
+    fingerprints.dedup();
+    let mut content = fingerprints.join("\n");
+    content.push('\n');
+    content.into_bytes()
+}
+
+fn is_trusted(validity: Validity) -> bool {
+    match validity {
+        Validity::Full | Validity::Ultimate => true,
+        _ => false,
+    }
+}
+
+fn is_among(fingerprint: &str, recipients: &[String]) -> bool {
+    recipients.iter().any(|r| fingerprint.ends_with(&r.to_uppercase()))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Verdict {
+    Unsigned,
+    Accepted,
+    MissingSignature,
+    UntrustedSigner,
+}
+
+/// Judge the recipients by the fingerprints of the `signers` with a good and trusted signature, or `None` if there
+/// is no signature at all. Signers must be among the `trusted` recipients, which are the ones last accepted.
+fn verdict(signers: Option<&[String]>, opted_in: bool, trusted: &[String]) -> Verdict {
+    match signers {
+        None if opted_in => Verdict::MissingSignature,
+        None => Verdict::Unsigned,
+        Some(signers) if signers.iter().any(|fpr| is_among(fpr, trusted)) => Verdict::Accepted,
+        Some(_) => Verdict::UntrustedSigner,
+    }
+}
+
+/// The directory holding the recipients you accepted last, per recipients file. It lives in the gpg home directory
+/// as it belongs to your keyring, just like the validity of the keys that sign the recipients.
+fn trusted_recipients_dir() -> Option<PathBuf> {
+    env::var_os("GNUPGHOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".gnupg")))
+        .map(|gpg_home| gpg_home.join(TRUSTED_RECIPIENTS_DIR))
+}
+
+impl Vault {
+    pub fn recipients_signature_path(&self) -> PathBuf {
+        let mut path = self.recipients_path().into_os_string();
+        path.push(SIGNATURE_SUFFIX);
+        PathBuf::from(path)
+    }
+
+    pub fn has_signed_recipients(&self) -> bool {
+        self.signed_recipients.unwrap_or(false) || self.recipients_signature_path().is_file()
+    }
+
+    fn trusted_recipients_path(&self) -> Option<PathBuf> {
+        let recipients_path = fs::canonicalize(self.recipients_path()).ok()?;
+        let mut path = trusted_recipients_dir()?;
+        path.extend(recipients_path.components().filter_map(|c| match c {
+            Component::Normal(c) => Some(c),
+            _ => None,
+        }));
+        Some(path)
+    }
+
+    /// The recipients you accepted last, or `None` if you never did.
+    pub fn trusted_recipients(&self) -> Result<Option<Vec<String>>, Error> {
+        let path = match self.trusted_recipients_path() {
+            Some(ref path) if path.is_file() => path.to_owned(),
+            _ => return Ok(None),
+        };
+        let content = fs::read_to_string(&path).context(format!(
+            "Could not read the recipients you accepted last at '{}'.",
+            path.display()
+        ))?;
+        Ok(Some(content.lines().filter(|l| !l.is_empty()).map(ToOwned::to_owned).collect()))
+    }
+
+    fn remember_trusted_recipients(&self, recipients: &[String]) -> Result<(), Error> {
+        let path = match self.trusted_recipients_path() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        path.parent()
+            .map(fs::create_dir_all)
+            .unwrap_or(Ok(()))
+            .and_then(|_| write_at(&path))
+            .and_then(|mut f| f.write_all(&signed_content(recipients)))
+            .context(format!(
+                "Could not remember the accepted recipients at '{}'.",
+                path.display()
+            ))?;
+        Ok(())
+    }
+
+    /// Fail unless the recipients are signed by one of the recipients you accepted last, using a key which is valid
+    /// in the gpg keyring. If you never accepted any, the signer must be one of the current recipients.
+    /// Recipients which were never signed are not verified, unless the vault says they must be signed or you accepted
+    /// signed recipients before.
+    pub fn verify_recipients_signature(&self, ctx: &mut gpgme::Context) -> Result<(), Error> {
+        let signature_path = self.recipients_signature_path();
+        let recipients = self.recipients_list()?;
+        let trusted = self.trusted_recipients()?;
+        let opted_in = self.signed_recipients.unwrap_or(false) || trusted.is_some();
+        let signers = if signature_path.is_file() {
+            let signature = fs::read(&signature_path).context(format!(
+                "Could not read signature of recipients at '{}'.",
+                signature_path.display()
+            ))?;
+            let result = ctx.verify_detached(signature, signed_content(&recipients))
+                .context(format!(
+                    "Could not verify signature of recipients at '{}'.",
+                    signature_path.display()
+                ))?;
+            Some(
+                result
+                    .signatures()
+                    .filter(|s| s.status().is_ok() && is_trusted(s.validity()))
+                    .filter_map(|s| s.fingerprint().ok().and_then(|fpr| ctx.find_key(fpr).ok()))
+                    .filter_map(|key| fingerprint_of(&key).ok())
+                    .collect::<Vec<_>>(),
+            )
+        } else {
+            None
+        };
+
+        match verdict(
+            signers.as_ref().map(Vec::as_slice),
+            opted_in,
+            trusted.as_ref().unwrap_or(&recipients),
+        ) {
+            Verdict::Unsigned => Ok(()),
+            Verdict::Accepted => self.remember_trusted_recipients(&recipients),
+            Verdict::MissingSignature => bail!(
+                "The recipients in '{}' must be signed, but their signature at '{}' is missing. \
+                 Someone might have removed it to add a recipient without permission. \
+                 Verify the recipients and run 'recipients sign' to accept them.",
+                self.recipients_path().display(),
+                signature_path.display()
+            ),
+            Verdict::UntrustedSigner => bail!(
+                "The recipients in '{}' are not signed by one of the recipients you accepted last, \
+                 using a key you trust, according to the signature at '{}'. \
+                 Someone might have added a recipient without permission. \
+                 Verify the recipients and run 'recipients sign' to accept them.",
+                self.recipients_path().display(),
+                signature_path.display()
+            ),
+        }
+    }
+
+    /// Sign the recipients with the secret key of one of them, so that they can be verified before encrypting.
+    /// The signed recipients are the ones you accept from now on.
+    pub fn sign_recipients(
+        &self,
+        ctx: &mut gpgme::Context,
+        signing_key_id: Option<&str>,
+        output: &mut Write,
+    ) -> Result<PathBuf, Error> {
+        let recipients = self.recipients_list()?;
+        let key = self.find_signing_key(ctx, signing_key_id).context(format!(
+            "Recipients in '{}' can only be signed by one of them.",
+            self.recipients_path().display()
+        ))?;
+        let mut signature = Vec::new();
+        ctx.set_armor(true);
+        ctx.clear_signers();
+        ctx.add_signer(&key)?;
+        let res = ctx.sign_detached(signed_content(&recipients), &mut signature);
+        ctx.clear_signers();
+        res.context("Could not sign the recipients.")?;
+
+        let signature_path = self.recipients_signature_path();
+        write_at(&signature_path)
+            .and_then(|mut f| f.write_all(&signature))
+            .context(format!(
+                "Could not write signature of recipients to '{}'.",
+                signature_path.display()
+            ))?;
+        self.remember_trusted_recipients(&recipients)?;
+        writeln!(
+            output,
+            "Signed {} recipient(s) in '{}' with key {}",
+            recipients.len(),
+            self.recipients_path().display(),
+            UserIdFingerprint(&key)
+        ).ok();
+        Ok(signature_path)
+    }
+
+    /// Sign the recipients again after they were changed, if they were signed before.
+    pub fn resign_recipients(&self, ctx: &mut gpgme::Context, output: &mut Write) -> Result<(), Error> {
+        if self.has_signed_recipients() {
+            self.sign_recipients(ctx, None, output)?;
+        }
+        Ok(())
+    }
+
+    /// Sign the recipients of the given partitions, or the selected one if there is none.
+    /// From then on, the vault requires these recipients to be signed.
+    pub fn sign_recipients_of(
+        &self,
+        partitions: &[String],
+        signing_key_id: Option<&str>,
+        output: &mut Write,
+    ) -> Result<(), Error> {
+        let mut ctx = new_context()?;
+        let mut changed = self.clone();
+        let mut opted_in = false;
+        for partition in self.partitions_by_name_or_path(partitions)? {
+            partition.sign_recipients(&mut ctx, signing_key_id, output)?;
+            if !partition.signed_recipients.unwrap_or(false) {
+                let vault = if changed.index == partition.index {
+                    &mut changed
+                } else {
+                    changed
+                        .partitions
+                        .iter_mut()
+                        .find(|p| p.index == partition.index)
+                        .expect("partitions to be cloned")
+                };
+                vault.signed_recipients = Some(true);
+                opted_in = true;
+            }
+        }
+        if opted_in {
+            changed.serialize()?;
+            writeln!(
+                output,
+                "Recipients must be signed from now on, as noted in '{}'",
+                changed.vault_path_for_display()
+            ).ok();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_signature {
+    use super::*;
+
+    #[test]
+    fn it_signs_sorted_fingerprints_without_duplicates() {
+        assert_eq!(
+            signed_content(&["BBBB".into(), "AAAA".into(), "BBBB".into()]),
+            b"AAAA\nBBBB\n".to_vec()
+        );
+    }
+
+    fn recipients() -> Vec<String> {
+        vec!["AAAA".into(), "BBBB".into()]
+    }
+
+    #[test]
+    fn it_refuses_a_deleted_signature_once_opted_in() {
+        assert_eq!(verdict(None, true, &recipients()), Verdict::MissingSignature);
+        assert_eq!(verdict(None, false, &recipients()), Verdict::Unsigned);
+    }
+
+    #[test]
+    fn it_refuses_a_tampered_list_that_was_not_signed_again() {
+        let no_good_signature: Vec<String> = Vec::new();
+        assert_eq!(
+            verdict(Some(&no_good_signature), true, &recipients()),
+            Verdict::UntrustedSigner
+        );
+    }
+
+    #[test]
+    fn it_refuses_a_tampered_list_signed_by_someone_who_was_not_accepted_before() {
+        let signed_by_intruder = vec!["0000CCCC".to_owned()];
+        assert_eq!(
+            verdict(Some(&signed_by_intruder), true, &recipients()),
+            Verdict::UntrustedSigner
+        );
+    }
+
+    #[test]
+    fn it_accepts_changes_signed_by_a_recipient_accepted_before() {
+        let signed_by_member = vec!["0000BBBB".to_owned()];
+        assert_eq!(verdict(Some(&signed_by_member), false, &recipients()), Verdict::Accepted);
+    }
+}
@@ -12,6 +12,10 @@ use mktemp::Temp;
 use error::EncryptionError;
 use util::write_at;
 use util::strip_ext;
+use versions::keep_version;
+use metadata::is_metadata_path;
+use stale::{last_commit_times, last_modified};
+use super::warn_about_expiring_keys;
 use print_causes;
 use TrustModel;
 use util::flags_for_model;
@@ -205,15 +209,26 @@ impl Vault {
         Ok((fpr_path, buf))
     }
 
+    /// Re-encrypt all resources of this partition for its recipients. `leader` is the vault owning it,
+    /// whose settings apply to the metadata of the resources.
     pub fn reencrypt(
         &self,
+        leader: &Vault,
         ctx: &mut gpgme::Context,
         model: &TrustModel,
+        required_certifications: usize,
+        keep_versions: usize,
         gpg_keys_dir: Option<&Path>,
         has_multiple_partitions: bool,
         output: &mut Write,
+        error: &mut Write,
     ) -> Result<(), Error> {
-        let keys = self.recipient_keys(ctx, gpg_keys_dir, output)?;
+        self.resign_recipients(ctx, output)?;
+        let keys = self.recipient_keys(ctx, gpg_keys_dir, error)?;
+        if let TrustModel::VaultMembers = *model {
+            self.verify_member_certifications(ctx, &keys, required_certifications, gpg_keys_dir, error)?;
+        }
+        warn_about_expiring_keys(&keys, error);
 
         let mut obuf = Vec::new();
 
@@ -229,6 +244,7 @@ impl Vault {
             let _change_cwd = ResetCWD::new(&secrets_dir)?;
             glob(GPG_GLOB).expect("valid pattern").filter_map(Result::ok).collect()
         };
+        let commit_times = last_commit_times(&secrets_dir);
         for encrypted_file_path in files_to_reencrypt {
             let tempfile = Temp::new_file().with_context(|_| {
                 format!(
@@ -258,6 +274,11 @@ impl Vault {
                         )
                     })?;
             }
+            if !is_metadata_path(&encrypted_file_path) {
+                let modified_at = last_modified(&secrets_dir, &encrypted_file_path, &commit_times)?;
+                leader.keep_update_time(self, &strip_ext(&encrypted_file_path), modified_at)?;
+                keep_version(&secrets_dir, &secrets_dir.join(&encrypted_file_path), keep_versions)?;
+            }
             write_at(&secrets_dir.join(&encrypted_file_path))
                 .with_context(|_| {
                     format!(
@@ -0,0 +1,215 @@
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use atty;
+use base::Vault;
+use failure::{Error, ResultExt};
+use spec::{Destination, SpecSourceType, VaultSpec, WriteMode};
+
+/// The name of the field referring to the first line of a record.
+pub const PASSWORD_FIELD: &str = "password";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Line {
+    Field { name: String, value: String },
+    Text(String),
+}
+
+/// A resource in the format used by `pass`: the first line is the password, and all following lines
+/// may be fields of the form `name: value`. Lines not looking like a field are kept as they are.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Record {
+    pub password: String,
+    lines: Vec<Line>,
+}
+
+fn is_password_field(name: &str) -> bool {
+    name.eq_ignore_ascii_case(PASSWORD_FIELD)
+}
+
+fn parse_line(line: &str) -> Line {
+    if let Some(pos) = line.find(':') {
+        let (name, rest) = (&line[..pos], &line[pos + 1..]);
+        let is_field = !name.is_empty() && !name.contains(char::is_whitespace)
+            && (rest.is_empty() || rest.starts_with(char::is_whitespace));
+        if is_field {
+            return Line::Field {
+                name: name.to_owned(),
+                value: rest.trim().to_owned(),
+            };
+        }
+    }
+    Line::Text(line.to_owned())
+}
+
+impl<'a> From<&'a str> for Record {
+    fn from(content: &'a str) -> Self {
+        let mut lines = content.lines();
+        Record {
+            password: lines.next().unwrap_or("").to_owned(),
+            lines: lines.map(parse_line).collect(),
+        }
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.password)?;
+        for line in &self.lines {
+            match *line {
+                Line::Field { ref name, ref value } => writeln!(f, "{}: {}", name, value),
+                Line::Text(ref text) => writeln!(f, "{}", text),
+            }?;
+        }
+        Ok(())
+    }
+}
+
+impl Record {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        if is_password_field(name) {
+            return Some(&self.password);
+        }
+        self.lines
+            .iter()
+            .filter_map(|l| match *l {
+                Line::Field {
+                    name: ref field_name,
+                    ref value,
+                } if field_name.eq_ignore_ascii_case(name) => Some(value.as_str()),
+                _ => None,
+            })
+            .next()
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) {
+        if is_password_field(name) {
+            self.password = value.to_owned();
+            return;
+        }
+        for line in &mut self.lines {
+            if let Line::Field {
+                name: ref field_name,
+                value: ref mut field_value,
+            } = *line
+            {
+                if field_name.eq_ignore_ascii_case(name) {
+                    *field_value = value.to_owned();
+                    return;
+                }
+            }
+        }
+        self.lines.push(Line::Field {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        });
+    }
+
+    /// All lines which are not fields, the password excluded.
+    pub fn text_lines<'a>(&'a self) -> Box<Iterator<Item = &'a str> + 'a> {
+        Box::new(self.lines.iter().filter_map(|l| match *l {
+            Line::Text(ref text) => Some(text.as_str()),
+            Line::Field { .. } => None,
+        }))
+    }
+}
+
+impl Vault {
+    pub fn decrypt_record(&self, path: &Path) -> Result<Record, Error> {
+        let mut buf = Vec::new();
+        self.decrypt(path, &mut buf)?;
+        let content = String::from_utf8(buf).context(format!(
+            "The resource at '{}' is not a text record as it is not valid UTF-8.",
+            path.display()
+        ))?;
+        Ok(Record::from(content.as_str()))
+    }
+
+    pub fn show_fields(&self, path: &Path, fields: &[String], output: &mut Write) -> Result<(), Error> {
+        let record = self.decrypt_record(path)?;
+        for field in fields {
+            let value = record
+                .get(field)
+                .ok_or_else(|| format_err!("There is no field named '{}' in '{}'", field, path.display()))?;
+            writeln!(output, "{}", value)?;
+        }
+        Ok(())
+    }
+
+    pub fn encrypt_records(
+        &self,
+        specs: &[VaultSpec],
+        fields: &[(String, String)],
+        mode: WriteMode,
+        output: &mut Write,
+        error: &mut Write,
+    ) -> Result<(), Error> {
+        let has_password_field = fields.iter().any(|&(ref name, _)| is_password_field(name));
+        let specs = specs
+            .iter()
+            .map(|spec| {
+                let mut content = String::new();
+                let skip_input =
+                    has_password_field && spec.src == SpecSourceType::Stdin && atty::is(atty::Stream::Stdin);
+                if !skip_input {
+                    spec.open_input()?.read_to_string(&mut content).context(format!(
+                        "Could not read all input for '{}' as UTF-8 text.",
+                        spec.destination().display()
+                    ))?;
+                }
+                let mut record = Record::from(content.as_str());
+                for &(ref name, ref value) in fields {
+                    record.set(name, value);
+                }
+                Ok(VaultSpec {
+                    src: SpecSourceType::Buffer(record.to_string().into_bytes()),
+                    dst: spec.dst.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        self.encrypt(&specs, mode, Destination::ReolveAndAppendGpg, output, error)
+    }
+}
+
+#[cfg(test)]
+mod tests_record {
+    use super::*;
+
+    const PASS_ENTRY: &str = "s3cr3t: yes\nusername: bob\nURL: https://example.com\notpauth://totp/x?secret=ABC\n";
+
+    #[test]
+    fn it_treats_the_first_line_as_password_even_if_it_looks_like_a_field() {
+        let r = Record::from(PASS_ENTRY);
+        assert_eq!(r.get("password"), Some("s3cr3t: yes"));
+        assert_eq!(r.get("PASSWORD"), Some("s3cr3t: yes"));
+    }
+
+    #[test]
+    fn it_finds_fields_case_insensitively_and_keeps_uris_as_text() {
+        let r = Record::from(PASS_ENTRY);
+        assert_eq!(r.get("username"), Some("bob"));
+        assert_eq!(r.get("url"), Some("https://example.com"));
+        assert_eq!(r.get("otpauth"), None);
+        assert_eq!(r.text_lines().collect::<Vec<_>>(), vec!["otpauth://totp/x?secret=ABC"]);
+    }
+
+    #[test]
+    fn it_updates_and_appends_fields_while_retaining_all_other_lines() {
+        let mut r = Record::from(PASS_ENTRY);
+        r.set("password", "new");
+        r.set("Username", "alice");
+        r.set("email", "alice@example.com");
+        assert_eq!(
+            r.to_string(),
+            "new\nusername: alice\nURL: https://example.com\notpauth://totp/x?secret=ABC\nemail: alice@example.com\n"
+        );
+    }
+
+    #[test]
+    fn it_builds_records_from_nothing() {
+        let mut r = Record::from("");
+        r.set("user", "bob");
+        assert_eq!(r.to_string(), "\nuser: bob\n");
+    }
+}
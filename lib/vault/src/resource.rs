@@ -1,9 +1,8 @@
 use std::io::{self, Write};
-use std::path::{Path, PathBuf};
-use std::fs::{remove_file, File};
+use std::path::{Component, Path, PathBuf};
+use std::fs::{self, remove_file, File};
 use std::mem;
 
-use mktemp::Temp;
 use itertools::join;
 use gpgme;
 use base::Vault;
@@ -12,11 +11,58 @@ use error::FailExt;
 use spec::{gpg_output_filename, SpecSourceType, VaultSpec};
 use spec::{CreateMode, Destination, WriteMode};
 use error::{DecryptionError, EncryptionError};
-use util::{new_context, strip_ext, write_at};
-use util::run_editor;
+use util::{new_context, strip_ext, write_at, ResetCWD};
+use util::merge_three_way;
+use editor::Editor;
+use prompt::confirm;
+use tempfile::{SecureTempDir, SecureTempFile};
+use glob::{glob, Pattern};
+use versions::keep_version;
+use metadata::is_metadata_path;
+use recipients::warn_about_expiring_keys;
 use std::iter::once;
 use TrustModel;
 use util::flags_for_model;
+use sha2::{Digest, Sha256};
+
+/// A resource whose decrypted content is being edited.
+struct EditedResource {
+    path: PathBuf,
+    encrypted_path: PathBuf,
+    hash_before_edit: Option<Vec<u8>>,
+    content_before_edit: Vec<u8>,
+}
+
+/// True if `path` is a valid glob pattern with special characters. This way, an unmatched '[' is taken literally.
+fn is_pattern(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    path.contains(|c| c == '*' || c == '?' || c == '[') && Pattern::new(&path).is_ok()
+}
+
+fn hash_of_file(path: &Path) -> Option<Vec<u8>> {
+    fs::read(path).ok().map(|content| Sha256::digest(&content).to_vec())
+}
+
+/// The path at which to store an edit which could not be written back due to a conflict.
+/// It never refers to an existing file, so earlier conflicts are kept.
+fn conflict_path_of(path: &Path) -> PathBuf {
+    let stem = match path.extension() {
+        Some(ext) if ext == "gpg" => strip_ext(path),
+        _ => path.to_owned(),
+    };
+    (1..)
+        .map(|n| {
+            let mut file_name = stem.file_name().expect("file name").to_owned();
+            if n == 1 {
+                file_name.push(".conflict.gpg");
+            } else {
+                file_name.push(format!(".conflict-{}.gpg", n));
+            }
+            stem.with_file_name(file_name)
+        })
+        .find(|p| !p.exists())
+        .expect("a free conflict path")
+}
 
 fn encrypt_buffer(
     ctx: &mut gpgme::Context,
@@ -35,53 +81,296 @@ impl Vault {
     pub fn edit(
         &self,
         path: &Path,
-        editor: &Path,
+        editor: &Editor,
         mode: &CreateMode,
+        template: Option<&str>,
         try_encrypt: bool,
         output: &mut Write,
+        error: &mut Write,
     ) -> Result<(), Error> {
-        let file = Temp::new_file().context("Could not create temporary file to decrypt to.")?;
-        let tempfile_path = file.to_path_buf();
+        let file = SecureTempFile::new().context("Could not create temporary file to decrypt to.")?;
+        let tempfile_path = file.path().to_owned();
         let decrypted_file_path = {
             let mut decrypted_writer =
                 write_at(&tempfile_path).context("Failed to open temporary file for writing decrypted content to.")?;
-            self.decrypt(path, &mut decrypted_writer)
-                .context(format!("Failed to decrypt file at '{}'.", path.display()))
-                .or_else(|err| match (mode, err.first_cause_of::<io::Error>()) {
-                    (&CreateMode::Create, Some(_)) => gpg_output_filename(path).and_then(|p| {
-                        self.partition_by_owned_path(p.clone())
-                            .map(|(partition, path)| partition.secrets_path().join(&path))
-                    }),
-                    _ => Err(err.into()),
-                })?
+            self.decrypt_or_create(path, mode, &mut decrypted_writer)?
         };
         if try_encrypt {
             let (partition, _) = self.partition_by_owned_path(decrypted_file_path.clone())?;
             self.encrypt_buffer(
+                partition,
                 b"",
                 self.gpg_keys_dir_for_auto_import(partition)
                     .as_ref()
                     .map(PathBuf::as_path),
-                output,
+                error,
             ).context("Aborted edit operation as you cannot encrypt resources.")?;
         }
-        run_editor(editor.as_os_str(), &tempfile_path)?;
-        let mut zero = Vec::new();
-        self.encrypt(
-            &[
-                VaultSpec {
-                    src: SpecSourceType::Path(tempfile_path.clone()),
-                    dst: decrypted_file_path,
+        let hash_before_edit = hash_of_file(&decrypted_file_path);
+        if let Some(name) = template {
+            if hash_before_edit.is_some() {
+                bail!(
+                    "A template can only be used when creating a resource, but '{}' exists already.",
+                    path.display()
+                )
+            }
+            let (partition, _) = self.partition_by_owned_path(path.to_owned())?;
+            let content = self.read_template(partition, name)?;
+            write_at(&tempfile_path)
+                .and_then(|mut f| f.write_all(&content))
+                .context("Failed to write template to temporary file.")?;
+        }
+        let resource = EditedResource {
+            path: path.to_owned(),
+            content_before_edit: fs::read(&tempfile_path).context("Could not read decrypted content.")?,
+            hash_before_edit,
+            encrypted_path: decrypted_file_path,
+        };
+        editor.run(&tempfile_path)?;
+        let edited_content = fs::read(&tempfile_path).context("Could not read edited content.")?;
+        self.write_back_edit(&resource, edited_content, output, error)
+    }
+
+    /// Decrypt the resource at `path` into `w`, or if it doesn't exist and `mode` allows it, leave `w` untouched.
+    /// Returns the path to the encrypted resource in both cases.
+    fn decrypt_or_create(&self, path: &Path, mode: &CreateMode, w: &mut Write) -> Result<PathBuf, Error> {
+        self.decrypt(path, w)
+            .context(format!("Failed to decrypt file at '{}'.", path.display()))
+            .or_else(|err| match (mode, err.first_cause_of::<io::Error>()) {
+                (&CreateMode::Create, Some(_)) => gpg_output_filename(path).and_then(|p| {
+                    self.partition_by_owned_path(p.clone())
+                        .map(|(partition, path)| partition.secrets_path().join(&path))
+                }),
+                _ => Err(err.into()),
+            })
+    }
+
+    /// Edit all resources at `paths` in a single editor session. Paths may be glob patterns, which only
+    /// match existing resources, while other paths are created according to `mode`.
+    pub fn edit_many(
+        &self,
+        paths: &[PathBuf],
+        editor: &Editor,
+        mode: &CreateMode,
+        template: Option<&str>,
+        try_encrypt: bool,
+        output: &mut Write,
+        error: &mut Write,
+    ) -> Result<(), Error> {
+        if paths.len() == 1 && !is_pattern(&paths[0]) {
+            return self.edit(&paths[0], editor, mode, template, try_encrypt, output, error);
+        }
+        if template.is_some() {
+            bail!("A template can only be used when editing a single resource.")
+        }
+        let dir = SecureTempDir::new().context("Could not create temporary directory to decrypt to.")?;
+        let mut resources = Vec::new();
+        let mut partitions = Vec::new();
+        for (path, mirrored_path) in self.resources_to_edit(paths)? {
+            let mut content = Vec::new();
+            let encrypted_path = self.decrypt_or_create(&path, mode, &mut content)?;
+            dir.create_file(&mirrored_path)
+                .and_then(|mut f| Ok(f.write_all(&content)?))
+                .context("Failed to write decrypted content to temporary directory.")?;
+            let (partition, _) = self.partition_by_owned_path(path.clone())?;
+            partitions.push(partition.index);
+            resources.push((
+                EditedResource {
+                    path,
+                    content_before_edit: content,
+                    hash_before_edit: hash_of_file(&encrypted_path),
+                    encrypted_path,
                 },
-            ],
-            WriteMode::AllowOverwrite,
-            Destination::Unchanged,
-            &mut zero,
-        ).context("Failed to re-encrypt edited content.")?;
-        writeln!(output, "Edited '{}'.", path.display()).ok();
+                dir.path().join(mirrored_path),
+            ));
+        }
+        if try_encrypt {
+            for partition in once(self).chain(&self.partitions) {
+                if partitions.contains(&partition.index) {
+                    self.encrypt_buffer(
+                        partition,
+                        b"",
+                        self.gpg_keys_dir_for_auto_import(partition)
+                            .as_ref()
+                            .map(PathBuf::as_path),
+                        error,
+                    ).context("Aborted edit operation as you cannot encrypt resources.")?;
+                }
+            }
+        }
+
+        editor.run_all(&resources.iter().map(|r| r.1.as_path()).collect::<Vec<_>>())?;
+        let mut failures = Vec::new();
+        for (resource, file) in resources {
+            let result = fs::read(&file)
+                .context("Could not read edited content.")
+                .map_err(Error::from)
+                .and_then(|edited_content| self.write_back_edit(&resource, edited_content, output, error));
+            if let Err(err) = result {
+                failures.push(format!("{}: {}", resource.path.display(), err));
+            }
+        }
+        if !failures.is_empty() {
+            bail!(
+                "Failed to write back {} edited resource(s):\n{}",
+                failures.len(),
+                failures.join("\n")
+            )
+        }
         Ok(())
     }
 
+    /// The resources at `paths`, with patterns expanded, paired with their path in a temporary directory.
+    /// Paths referring to the same resource, like `foo` and `foo.gpg`, are only listed once.
+    fn resources_to_edit(&self, paths: &[PathBuf]) -> Result<Vec<(PathBuf, PathBuf)>, Error> {
+        let mut expanded = Vec::new();
+        for path in paths {
+            if is_pattern(path) {
+                expanded.extend(self.resources_matching(path)?);
+            } else {
+                expanded.push(path.to_owned());
+            }
+        }
+        expanded.sort();
+
+        let mut resources: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for path in expanded {
+            let mirrored_path = self.mirrored_path_of(&path)?;
+            if !resources.iter().any(|&(_, ref m)| *m == mirrored_path) {
+                resources.push((path, mirrored_path));
+            }
+        }
+        Ok(resources)
+    }
+
+    /// All resources matching the glob `pattern`, with paths as they would be used to refer to them.
+    fn resources_matching(&self, pattern: &Path) -> Result<Vec<PathBuf>, Error> {
+        let (partition, relative_pattern) = self.partition_by_owned_path(pattern.to_owned())?;
+        let dir = partition.secrets_path();
+        let mut relative_pattern = relative_pattern.to_string_lossy().into_owned();
+        if !relative_pattern.ends_with(".gpg") {
+            relative_pattern.push_str(".gpg");
+        }
+        let matches: Vec<_> = {
+            let _change_cwd = ResetCWD::new(&dir)?;
+            glob(&relative_pattern)
+                .context(format!("Invalid glob pattern '{}'.", pattern.display()))?
+                .filter_map(Result::ok)
+                .filter(|p| p.is_file() && !is_metadata_path(p))
+                .map(|p| {
+                    let resource = strip_ext(&p);
+                    if self.partitions.is_empty() {
+                        resource
+                    } else {
+                        partition.secrets.join(resource)
+                    }
+                })
+                .collect()
+        };
+        if matches.is_empty() {
+            bail!("No resource matches '{}'.", pattern.display())
+        }
+        Ok(matches)
+    }
+
+    /// The vault-relative path of the resource at `path`, for use in a temporary directory.
+    fn mirrored_path_of(&self, path: &Path) -> Result<PathBuf, Error> {
+        let (partition, relative_path) = self.partition_by_owned_path(path.to_owned())?;
+        let relative_path = match relative_path.extension() {
+            Some(ext) if ext == "gpg" => strip_ext(&relative_path),
+            _ => relative_path,
+        };
+        let relative_path = relative_path
+            .strip_prefix(partition.secrets_path())
+            .map(ToOwned::to_owned)
+            .unwrap_or(relative_path);
+        let mirrored_path = if self.partitions.is_empty() {
+            relative_path
+        } else {
+            partition.secrets.join(relative_path)
+        };
+        Ok(mirrored_path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(c) => Some(c),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Encrypt `edited_content` of `resource`. If it was changed in the meantime, the merged content is shown and
+    /// stored if you agree, otherwise the edit is saved next to the resource. Warnings about the keys of recipients are written to `error`.
+    fn write_back_edit(
+        &self,
+        resource: &EditedResource,
+        edited_content: Vec<u8>,
+        output: &mut Write,
+        error: &mut Write,
+    ) -> Result<(), Error> {
+        let path = &resource.path;
+        if resource.hash_before_edit.is_some() && edited_content == resource.content_before_edit {
+            writeln!(output, "No changes to '{}'.", path.display()).ok();
+            return Ok(());
+        }
+
+        let store = |content: Vec<u8>, dst: PathBuf, mode: WriteMode, error: &mut Write| {
+            self.encrypt(
+                &[
+                    VaultSpec {
+                        src: SpecSourceType::Buffer(content),
+                        dst,
+                    },
+                ],
+                mode,
+                Destination::Unchanged,
+                &mut io::sink(),
+                error,
+            )
+        };
+        if hash_of_file(&resource.encrypted_path) == resource.hash_before_edit {
+            store(edited_content, resource.encrypted_path.clone(), WriteMode::AllowOverwrite, error)
+                .context("Failed to re-encrypt edited content.")?;
+            writeln!(output, "Edited '{}'.", path.display()).ok();
+            return Ok(());
+        }
+
+        let mut current_content = Vec::new();
+        self.decrypt(path, &mut current_content).context(format!(
+            "Failed to decrypt '{}' which changed while it was edited.",
+            path.display()
+        ))?;
+        let merged = merge_three_way(&resource.content_before_edit, &edited_content, &current_content)?;
+        if let Some(merged_content) = merged {
+            writeln!(
+                output,
+                "'{}' was changed while you were editing it. Your edit merges with these changes into:",
+                path.display()
+            ).ok();
+            output.write_all(&merged_content).ok();
+            output.flush().ok();
+            if confirm("Store the merged content?", error)? {
+                store(merged_content, resource.encrypted_path.clone(), WriteMode::AllowOverwrite, error)
+                    .context("Failed to re-encrypt merged content.")?;
+                writeln!(
+                    output,
+                    "Merged your edit with changes made to '{}' in the meantime.",
+                    path.display()
+                ).ok();
+                return Ok(());
+            }
+        }
+
+        let conflict_path = conflict_path_of(&resource.encrypted_path);
+        store(edited_content, conflict_path.clone(), WriteMode::RefuseOverwrite, error)
+            .context("Failed to save edited content to a separate resource.")?;
+        bail!(
+            "'{}' was changed while you were editing it and your edit was not merged. \
+             Your edit was saved to '{}' instead.",
+            path.display(),
+            conflict_path.display()
+        )
+    }
+
     pub fn decrypt(&self, path: &Path, w: &mut Write) -> Result<PathBuf, Error> {
         let mut ctx = new_context()?;
         let (partition, path) = self.partition_by_owned_path(path.to_owned())?;
@@ -123,26 +412,36 @@ impl Vault {
                 }
             };
             remove_file(&path).context(format!("Failed to remove file at '{}'.", path.display()))?;
+            self.remove_metadata(partition, &path)?;
+            self.forget_tags(partition, &path)?;
             writeln!(output, "Removed file at '{}'", path.display()).ok();
         }
         Ok(())
     }
 
+    /// Encrypt `input` for the recipients of `partition`, which is this vault or one of its partitions.
     pub fn encrypt_buffer(
         &self,
+        partition: &Vault,
         input: &[u8],
         gpg_keys_dir: Option<&Path>,
         output: &mut io::Write,
     ) -> Result<Vec<u8>, Error> {
         let mut ctx = new_context()?;
-        let keys = self.recipient_keys(&mut ctx, gpg_keys_dir, output)?;
-
-        let encrypted_bytes = encrypt_buffer(
-            &mut ctx,
-            input,
-            &keys,
-            &self.trust_model.clone().unwrap_or_else(TrustModel::default),
-        )?;
+        let keys = partition.recipient_keys(&mut ctx, gpg_keys_dir, output)?;
+        warn_about_expiring_keys(&keys, output);
+        let model = self.find_trust_model(partition);
+        if let TrustModel::VaultMembers = model {
+            partition.verify_member_certifications(
+                &mut ctx,
+                &keys,
+                self.find_required_certifications(partition),
+                gpg_keys_dir,
+                output,
+            )?;
+        }
+
+        let encrypted_bytes = encrypt_buffer(&mut ctx, input, &keys, &model)?;
         Ok(encrypted_bytes)
     }
 
@@ -181,6 +480,7 @@ impl Vault {
         mode: WriteMode,
         dst_mode: Destination,
         output: &mut Write,
+        error: &mut Write,
     ) -> Result<(), Error> {
         let mut ctx = new_context()?;
         let mut lut: Vec<Option<(PathBuf, Vec<gpgme::Key>)>> = vec![None; 1 + self.partitions.len()];
@@ -193,19 +493,21 @@ impl Vault {
                     &mut Some((ref secrets_dir, ref keys)) => (secrets_dir, keys),
                     none => {
                         let gpg_keys_dir = self.gpg_keys_dir_for_auto_import(partition);
-                        mem::replace(
-                            none,
-                            Some((
-                                partition.secrets_path(),
-                                partition.recipient_keys(
-                                    &mut ctx,
-                                    gpg_keys_dir.as_ref().map(PathBuf::as_path),
-                                    output,
-                                )?,
-                            )),
-                        );
+                        let gpg_keys_dir = gpg_keys_dir.as_ref().map(PathBuf::as_path);
+                        let keys = partition.recipient_keys(&mut ctx, gpg_keys_dir, error)?;
+                        if let TrustModel::VaultMembers = self.find_trust_model(partition) {
+                            partition.verify_member_certifications(
+                                &mut ctx,
+                                &keys,
+                                self.find_required_certifications(partition),
+                                gpg_keys_dir,
+                                error,
+                            )?;
+                        }
+                        mem::replace(none, Some((partition.secrets_path(), keys)));
                         let some = none;
                         let &(ref secrets_dir, ref keys) = some.as_ref().expect("the content that was just put in");
+                        warn_about_expiring_keys(keys, error);
                         (secrets_dir, keys)
                     }
                 };
@@ -220,12 +522,19 @@ impl Vault {
                     buf
                 };
                 let mut encrypted_bytes = encrypt_buffer(&mut ctx, &input, keys, &self.find_trust_model(partition))?;
+                let output_file = spec.output_in(secrets_dir, dst_mode)?;
+                if !mode.refuse_overwrite() {
+                    keep_version(secrets_dir, &output_file, self.find_keep_versions(partition))?;
+                }
                 spec.open_output_in(secrets_dir, mode, dst_mode, output)?
                     .write_all(&encrypted_bytes)
                     .context(format!(
                         "Failed to write all encrypted data to '{}'.",
                         spec.destination().display(),
                     ))?;
+                if let Ok(resource) = output_file.strip_prefix(secrets_dir) {
+                    self.touch_metadata(partition, &strip_ext(resource))?;
+                }
             }
             encrypted_destinations.push(spec.destination());
         }
@@ -240,3 +549,59 @@ impl Vault {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests_resource {
+    use super::*;
+    use mktemp::Temp;
+
+    #[test]
+    fn it_takes_unmatched_brackets_literally() {
+        assert!(is_pattern(Path::new("db/*")));
+        assert!(is_pattern(Path::new("db/[ab]")));
+        assert!(!is_pattern(Path::new("db/[literal")));
+        assert!(!is_pattern(Path::new("db/password")));
+    }
+
+    #[test]
+    fn it_edits_each_of_several_resources_once() {
+        let tmp = Temp::new_dir().unwrap();
+        let vault = Vault {
+            resolved_at: tmp.to_path_buf(),
+            ..Default::default()
+        };
+        fs::create_dir_all(tmp.to_path_buf().join("db")).unwrap();
+        for resource in &["db/password.gpg", "db/user.gpg", "foo.gpg"] {
+            fs::write(tmp.to_path_buf().join(resource), b"").unwrap();
+        }
+        let paths: Vec<PathBuf> = ["foo", "foo.gpg", "db/*", "db/user.gpg", "new"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let mirrored: Vec<_> = vault
+            .resources_to_edit(&paths)
+            .unwrap()
+            .into_iter()
+            .map(|(_, m)| m)
+            .collect();
+        assert_eq!(
+            mirrored,
+            vec![
+                PathBuf::from("db/password"),
+                PathBuf::from("db/user"),
+                PathBuf::from("foo"),
+                PathBuf::from("new"),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_never_uses_the_path_of_an_earlier_conflict() {
+        let tmp = Temp::new_dir().unwrap();
+        let resource = tmp.to_path_buf().join("password.gpg");
+        let first = conflict_path_of(&resource);
+        assert_eq!(first, tmp.to_path_buf().join("password.conflict.gpg"));
+        fs::write(&first, b"first").unwrap();
+        assert_eq!(conflict_path_of(&resource), tmp.to_path_buf().join("password.conflict-2.gpg"));
+    }
+}
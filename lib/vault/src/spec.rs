@@ -1,32 +1,27 @@
 use conv::TryFrom;
 use std::fmt;
 use std::fs::{File, OpenOptions};
-use std::io::{self, stdin, Read, Write};
+use std::io::{self, stdin, Cursor, Read, Write};
 use std::fs::create_dir_all;
 
 use atty;
-use mktemp::Temp;
 use failure::{Error, ResultExt};
 use std::path::{Path, PathBuf};
 use std::path::Component;
-use std::env;
-use std::ffi::OsString;
-use util::run_editor;
-
-lazy_static! {
-    static ref EDITOR: PathBuf = PathBuf::from(env::var_os("EDITOR").unwrap_or_else(|| OsString::from("vim")));
-}
+use editor::Editor;
+use tempfile::SecureTempFile;
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum SpecSourceType {
     Stdin,
     Path(PathBuf),
+    Buffer(Vec<u8>),
 }
 
 impl fmt::Display for SpecSourceType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            SpecSourceType::Stdin => {
+            SpecSourceType::Stdin | SpecSourceType::Buffer(_) => {
                 let empty = PathBuf::new();
                 empty.display().fmt(f)
             }
@@ -72,7 +67,7 @@ pub fn gpg_output_filename(path: &Path) -> Result<PathBuf, Error> {
 }
 
 struct TemporaryFile {
-    _tempfile: Temp,
+    _tempfile: SecureTempFile,
     open_file: File,
 }
 
@@ -85,7 +80,7 @@ impl Read for TemporaryFile {
 impl VaultSpec {
     pub fn source(&self) -> Option<&Path> {
         match self.src {
-            SpecSourceType::Stdin => None,
+            SpecSourceType::Stdin | SpecSourceType::Buffer(_) => None,
             SpecSourceType::Path(ref p) => Some(p.as_path()),
         }
     }
@@ -140,11 +135,13 @@ impl VaultSpec {
             SpecSourceType::Path(ref p) => {
                 Box::new(File::open(p).with_context(|_| format!("Could not open input file at '{}'", p.display()))?)
             }
+            SpecSourceType::Buffer(ref buf) => Box::new(Cursor::new(buf.clone())),
             SpecSourceType::Stdin => {
                 if atty::is(atty::Stream::Stdin) {
-                    let tempfile = Temp::new_file().with_context(|_| "Failed to obtain temporary file for editing.")?;
-                    let tempfile_path = tempfile.to_path_buf();
-                    run_editor(EDITOR.as_os_str(), &tempfile_path)?;
+                    let tempfile =
+                        SecureTempFile::new().with_context(|_| "Failed to obtain temporary file for editing.")?;
+                    let tempfile_path = tempfile.path().to_owned();
+                    Editor::from_env().run(&tempfile_path)?;
                     Box::new(TemporaryFile {
                         _tempfile: tempfile,
                         open_file: File::open(&tempfile_path).with_context(|_| {
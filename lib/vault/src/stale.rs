@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::iter::once;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base::Vault;
+use chrono::{DateTime, Local, NaiveDate};
+use failure::{Error, ResultExt};
+use metadata::{Metadata, DATE_FORMAT, ROTATE_AFTER, UPDATED};
+use spec::gpg_output_filename;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Parse the output of `git log --format=%x00%ct --name-only` into the time of the latest commit of each file.
+fn parse_commit_times(log: &str) -> HashMap<PathBuf, u64> {
+    let mut times = HashMap::new();
+    let mut time = None;
+    for line in log.lines() {
+        if line.starts_with('\0') {
+            time = line[1..].trim().parse().ok();
+        } else if let (Some(time), false) = (time, line.is_empty()) {
+            times.entry(PathBuf::from(line)).or_insert(time);
+        }
+    }
+    times
+}
+
+/// The time of the last commit touching each file below `dir`, relative to it, if it is tracked in a git repository.
+pub fn last_commit_times(dir: &Path) -> HashMap<PathBuf, u64> {
+    Command::new("git")
+        .args(&["log", "--format=%x00%ct", "--name-only", "--relative", "--", "."])
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|log| parse_commit_times(&log))
+        .unwrap_or_default()
+}
+
+/// The time the file at `path` relative to `dir` was last modified, preferring the git history in `commit_times`
+/// over the file system as the modification time of files is reset when they are checked out.
+pub fn last_modified(dir: &Path, path: &Path, commit_times: &HashMap<PathBuf, u64>) -> Result<u64, Error> {
+    if let Some(time) = commit_times.get(path) {
+        return Ok(*time);
+    }
+    let path = dir.join(path);
+    Ok(fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .context(format!("Could not obtain modification time of '{}'.", path.display()))?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0))
+}
+
+/// The time a resource was last written according to its metadata. Unlike the modification time of its file,
+/// it is not affected by re-encrypting the resource for other recipients.
+fn updated_at(metadata: &Metadata) -> Option<u64> {
+    DateTime::parse_from_rfc3339(metadata.get(UPDATED)?)
+        .ok()
+        .map(|t| t.timestamp().max(0) as u64)
+}
+
+/// The reasons for which a resource last modified at `modified_at` needs to be rotated.
+fn reasons_to_rotate(
+    modified_at: u64,
+    rotate_after: Option<&str>,
+    now: u64,
+    today: NaiveDate,
+    max_age_days: Option<u64>,
+) -> Result<Vec<String>, Error> {
+    let mut reasons = Vec::new();
+    if let Some(max_age_days) = max_age_days {
+        let age_days = now.saturating_sub(modified_at) / SECONDS_PER_DAY;
+        if age_days > max_age_days {
+            reasons.push(format!("not modified for {} days", age_days));
+        }
+    }
+    if let Some(rotate_after) = rotate_after {
+        let deadline = NaiveDate::parse_from_str(rotate_after, DATE_FORMAT)
+            .context(format!("Invalid '{}' date: '{}'", ROTATE_AFTER, rotate_after))?;
+        if deadline < today {
+            reasons.push(format!("rotation was due on {}", rotate_after));
+        }
+    }
+    Ok(reasons)
+}
+
+impl Vault {
+    /// Print all resources older than `max_age_days` or past their rotation date, grouped by partition.
+    /// Fails if there is at least one such resource.
+    pub fn print_stale(&self, max_age_days: Option<u64>, output: &mut Write) -> Result<(), Error> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("The system time is before the unix epoch.")?
+            .as_secs();
+        let today = Local::today().naive_local();
+        let has_multiple_partitions = !self.partitions.is_empty();
+        let mut num_stale = 0;
+        for partition in once(self).chain(self.partitions.iter()) {
+            let dir = partition.secrets_path();
+            let mut printed_partition = false;
+            let commit_times = last_commit_times(&dir);
+            for resource in partition.resources()? {
+                let metadata = self.read_metadata(partition, &resource)?.unwrap_or_default();
+                let modified_at = match updated_at(&metadata) {
+                    Some(time) => time,
+                    None => last_modified(&dir, &gpg_output_filename(&resource)?, &commit_times)?,
+                };
+                let reasons = reasons_to_rotate(
+                    modified_at,
+                    metadata.get(ROTATE_AFTER).map(String::as_str),
+                    now,
+                    today,
+                    max_age_days,
+                ).context(format!("Could not check '{}' for staleness.", resource.display()))?;
+                if reasons.is_empty() {
+                    continue;
+                }
+                if !printed_partition {
+                    writeln!(output, "{}", partition.url())?;
+                    printed_partition = true;
+                }
+                let resource = if has_multiple_partitions {
+                    dir.join(&resource)
+                } else {
+                    resource
+                };
+                writeln!(output, "{}\t{}", resource.display(), reasons.join(", "))?;
+                num_stale += 1;
+            }
+        }
+        if num_stale > 0 {
+            bail!("{} resource(s) are due for rotation.", num_stale)
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_stale {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd(2018, 6, 1)
+    }
+
+    #[test]
+    fn it_reports_resources_older_than_the_maximum_age() {
+        let now = 100 * SECONDS_PER_DAY;
+        assert_eq!(
+            reasons_to_rotate(10 * SECONDS_PER_DAY, None, now, today(), Some(30)).unwrap(),
+            vec!["not modified for 90 days"]
+        );
+        assert!(
+            reasons_to_rotate(80 * SECONDS_PER_DAY, None, now, today(), Some(30))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(reasons_to_rotate(0, None, now, today(), None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_finds_the_latest_commit_of_each_file() {
+        let log = "\x00300\n\nb.gpg\n\x00200\n\na.gpg\nb.gpg\n";
+        let times = parse_commit_times(log);
+        assert_eq!(times.get(Path::new("a.gpg")), Some(&200));
+        assert_eq!(times.get(Path::new("b.gpg")), Some(&300));
+        assert_eq!(times.len(), 2);
+    }
+
+    #[test]
+    fn it_prefers_the_update_time_of_the_metadata() {
+        let mut metadata = Metadata::new();
+        assert_eq!(updated_at(&metadata), None);
+        metadata.insert(UPDATED.into(), "1970-01-02T00:00:00Z".into());
+        assert_eq!(updated_at(&metadata), Some(SECONDS_PER_DAY));
+    }
+
+    #[test]
+    fn it_reports_resources_past_their_rotation_date() {
+        assert_eq!(
+            reasons_to_rotate(0, Some("2018-05-31"), 0, today(), None).unwrap(),
+            vec!["rotation was due on 2018-05-31"]
+        );
+        assert!(
+            reasons_to_rotate(0, Some("2018-06-01"), 0, today(), None)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(reasons_to_rotate(0, Some("soon"), 0, today(), None).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use base::Vault;
+use failure::{Error, ResultExt};
+use serde_yaml;
+use util::{strip_ext, write_at};
+
+/// The file within the secrets directory of a vault which maps resources to their tags.
+pub const TAGS_FILE: &str = ".tags.yml";
+
+pub type TagIndex = BTreeMap<String, BTreeSet<String>>;
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum TagChange {
+    Add(String),
+    Remove(String),
+}
+
+fn valid_tag(tag: &str) -> Result<String, Error> {
+    if tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || c == ',') {
+        bail!("'{}' is not a valid tag, it must not be empty or contain whitespace or commas", tag)
+    }
+    Ok(tag.to_owned())
+}
+
+impl TagChange {
+    pub fn add(tag: &str) -> Result<Self, Error> {
+        valid_tag(tag).map(TagChange::Add)
+    }
+
+    pub fn remove(tag: &str) -> Result<Self, Error> {
+        valid_tag(tag).map(TagChange::Remove)
+    }
+}
+
+fn key_of(resource: &Path) -> String {
+    resource.to_string_lossy().into_owned()
+}
+
+fn apply(index: &mut TagIndex, resource: &Path, changes: &[TagChange]) {
+    let key = key_of(resource);
+    {
+        let tags = index.entry(key.clone()).or_insert_with(BTreeSet::new);
+        for change in changes {
+            match *change {
+                TagChange::Add(ref tag) => tags.insert(tag.to_owned()),
+                TagChange::Remove(ref tag) => tags.remove(tag),
+            };
+        }
+    }
+    if index[&key].is_empty() {
+        index.remove(&key);
+    }
+}
+
+impl Vault {
+    pub fn tags_path(&self) -> PathBuf {
+        self.secrets_path().join(TAGS_FILE)
+    }
+
+    pub fn read_tags(&self) -> Result<TagIndex, Error> {
+        let path = self.tags_path();
+        if !path.is_file() {
+            return Ok(TagIndex::new());
+        }
+        let content = fs::read(&path).context(format!("Could not read tags at '{}'.", path.display()))?;
+        Ok(serde_yaml::from_slice(&content).context(format!("Could not parse tags at '{}'.", path.display()))?)
+    }
+
+    fn write_tags(&self, index: &TagIndex) -> Result<(), Error> {
+        let path = self.tags_path();
+        if index.is_empty() {
+            if path.is_file() {
+                fs::remove_file(&path).context(format!("Could not remove tags at '{}'.", path.display()))?;
+            }
+            return Ok(());
+        }
+        let yaml = serde_yaml::to_vec(index).context("Could not serialize tags.")?;
+        write_at(&path)
+            .and_then(|mut f| f.write_all(&yaml))
+            .context(format!("Could not write tags to '{}'.", path.display()))?;
+        Ok(())
+    }
+
+    /// The tags of `resource` in `partition`, as relative to its secrets directory.
+    pub fn tags_of(&self, partition: &Vault, resource: &Path) -> Result<BTreeSet<String>, Error> {
+        Ok(partition
+            .read_tags()?
+            .remove(&key_of(resource))
+            .unwrap_or_else(BTreeSet::new))
+    }
+
+    /// Remove the resource whose encrypted file is at `encrypted_path` from the tag index.
+    pub fn forget_tags(&self, partition: &Vault, encrypted_path: &Path) -> Result<(), Error> {
+        let resource = match encrypted_path.strip_prefix(partition.secrets_path()) {
+            Ok(resource) => strip_ext(resource),
+            Err(_) => return Ok(()),
+        };
+        let mut index = partition.read_tags()?;
+        if index.remove(&key_of(&resource)).is_some() {
+            partition.write_tags(&index)?;
+        }
+        Ok(())
+    }
+
+    pub fn tag(&self, path: &Path, changes: &[TagChange], output: &mut Write) -> Result<(), Error> {
+        let (partition, resource) = self.resource_of(path)?;
+        if !changes.is_empty() {
+            let mut file_name = resource.file_name().expect("resource with file name").to_owned();
+            file_name.push(".gpg");
+            if !partition.secrets_path().join(&resource).with_file_name(file_name).is_file() {
+                bail!("There is no resource at '{}'.", path.display())
+            }
+            let mut index = partition.read_tags()?;
+            apply(&mut index, &resource, changes);
+            partition.write_tags(&index)?;
+        }
+        let tags = self.tags_of(partition, &resource)?;
+        writeln!(
+            output,
+            "{}",
+            tags.iter().map(|t| format!("+{}", t)).collect::<Vec<_>>().join(" ")
+        ).ok();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_tags {
+    use super::*;
+
+    #[test]
+    fn it_validates_tags() {
+        assert_eq!(TagChange::add("prod").unwrap(), TagChange::Add("prod".into()));
+        assert_eq!(TagChange::remove("h").unwrap(), TagChange::Remove("h".into()));
+        assert!(TagChange::add("").is_err());
+        assert!(TagChange::add("a b").is_err());
+        assert!(TagChange::remove("a,b").is_err());
+    }
+
+    #[test]
+    fn it_adds_and_removes_tags_and_drops_untagged_resources() {
+        let mut index = TagIndex::new();
+        let resource = Path::new("db/password");
+        apply(
+            &mut index,
+            resource,
+            &[TagChange::Add("prod".into()), TagChange::Add("db".into())],
+        );
+        assert_eq!(
+            index["db/password"].iter().collect::<Vec<_>>(),
+            vec!["db", "prod"]
+        );
+        apply(
+            &mut index,
+            resource,
+            &[TagChange::Remove("prod".into()), TagChange::Remove("db".into())],
+        );
+        assert!(index.is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+use std::cmp;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::io::RawFd;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+use std::sync::{Mutex, Once, ONCE_INIT};
+use std::thread;
+
+use failure::{Error, ResultExt};
+use libc;
+use rand;
+use signal_hook::iterator::Signals;
+use signal_hook::{SIGINT, SIGTERM};
+
+lazy_static! {
+    static ref LIVE_FILES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+    static ref TERMINAL_TO_RESTORE: Mutex<Option<(RawFd, libc::termios)>> = Mutex::new(None);
+}
+static INSTALL_SIGNAL_HANDLER: Once = ONCE_INIT;
+static EDITOR_IS_RUNNING: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// A temporary file to hold decrypted content. It is placed in a memory-backed directory if possible,
+/// is only accessible by the current user and gets overwritten before it is removed, which also
+/// happens if the process is interrupted or terminated.
+pub struct SecureTempFile {
+    path: PathBuf,
+}
+
+/// Prefer directories which are not backed by a disk, to make it less likely for secrets to be persisted.
+fn secure_temp_dir() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(Some(PathBuf::from("/dev/shm")))
+        .find(|d| d.is_dir())
+        .unwrap_or_else(env::temp_dir)
+}
+
+fn wipe(path: &Path) -> io::Result<()> {
+    let mut remaining = fs::metadata(path)?.len();
+    {
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        let zeros = [0u8; 4096];
+        while remaining > 0 {
+            let len = cmp::min(remaining, zeros.len() as u64) as usize;
+            file.write_all(&zeros[..len])?;
+            remaining -= len as u64;
+        }
+        file.sync_all()?;
+    }
+    fs::remove_file(path)
+}
+
+/// Wipe the file at `path`, or all files below it if it is a directory, and remove it.
+fn wipe_path(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            wipe_path(&entry?.path())?;
+        }
+        fs::remove_dir(path)
+    } else {
+        wipe(path).or_else(|_| fs::remove_file(path))
+    }
+}
+
+fn unique_path() -> PathBuf {
+    secure_temp_dir().join(format!("sy-{}-{:016x}", process::id(), rand::random::<u64>()))
+}
+
+fn register(path: &Path) {
+    install_signal_handler();
+    LIVE_FILES
+        .lock()
+        .expect("no panic while holding the lock")
+        .push(path.to_owned());
+}
+
+fn unregister_and_wipe(path: &Path) {
+    if let Ok(mut files) = LIVE_FILES.lock() {
+        files.retain(|p| p != path);
+    }
+    wipe_path(path).or_else(|_| fs::remove_dir_all(path)).ok();
+}
+
+fn install_signal_handler() {
+    INSTALL_SIGNAL_HANDLER.call_once(|| {
+        if let Ok(signals) = Signals::new(&[SIGINT, SIGTERM]) {
+            thread::spawn(move || {
+                for signal in signals.forever() {
+                    // The editor shares our terminal and handles interrupts on its own
+                    if signal == SIGINT && EDITOR_IS_RUNNING.load(Ordering::SeqCst) {
+                        continue;
+                    }
+                    if let Ok(files) = LIVE_FILES.lock() {
+                        for path in files.iter() {
+                            wipe_path(path).ok();
+                        }
+                    }
+                    restore_terminal();
+                    process::exit(128 + signal);
+                }
+            });
+        }
+    });
+}
+
+/// Apply the terminal settings remembered by a live `TerminalGuard`, if there is one.
+pub fn restore_terminal() {
+    if let Ok(terminal) = TERMINAL_TO_RESTORE.lock() {
+        if let Some((fd, ref original)) = *terminal {
+            unsafe {
+                libc::tcsetattr(fd, libc::TCSANOW, original);
+            }
+        }
+    }
+}
+
+/// While alive, the `original` settings of the terminal at `fd` are restored if the process is interrupted
+/// or terminated. Restoring them when the guard is dropped is left to its owner.
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn new(fd: RawFd, original: libc::termios) -> Self {
+        install_signal_handler();
+        *TERMINAL_TO_RESTORE.lock().expect("no panic while holding the lock") = Some((fd, original));
+        TerminalGuard
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if let Ok(mut terminal) = TERMINAL_TO_RESTORE.lock() {
+            *terminal = None;
+        }
+    }
+}
+
+impl SecureTempFile {
+    pub fn new() -> Result<Self, Error> {
+        let path = unique_path();
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .with_context(|_| format!("Could not create temporary file at '{}'.", path.display()))?;
+        register(&path);
+        Ok(SecureTempFile { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for SecureTempFile {
+    fn drop(&mut self) {
+        unregister_and_wipe(&self.path);
+    }
+}
+
+/// Like `SecureTempFile`, but a directory only accessible by the current user.
+/// All files within it are wiped when it is dropped.
+pub struct SecureTempDir {
+    path: PathBuf,
+}
+
+impl SecureTempDir {
+    pub fn new() -> Result<Self, Error> {
+        let path = unique_path();
+        fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&path)
+            .with_context(|_| format!("Could not create temporary directory at '{}'.", path.display()))?;
+        register(&path);
+        Ok(SecureTempDir { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Create a file at the relative `path` within this directory, along with all intermediate directories.
+    pub fn create_file(&self, path: &Path) -> Result<fs::File, Error> {
+        let path = self.path.join(path);
+        if let Some(dir) = path.parent() {
+            fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)
+                .with_context(|_| format!("Could not create temporary directory at '{}'.", dir.display()))?;
+        }
+        Ok(OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .with_context(|_| format!("Could not create temporary file at '{}'.", path.display()))?)
+    }
+}
+
+impl Drop for SecureTempDir {
+    fn drop(&mut self) {
+        unregister_and_wipe(&self.path);
+    }
+}
+
+/// While alive, interrupts are left to the editor instead of ending the program.
+pub struct EditorSession;
+
+impl EditorSession {
+    pub fn new() -> Self {
+        EDITOR_IS_RUNNING.store(true, Ordering::SeqCst);
+        EditorSession
+    }
+}
+
+impl Drop for EditorSession {
+    fn drop(&mut self) {
+        EDITOR_IS_RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests_tempfile {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn it_creates_files_only_readable_by_the_owner_and_removes_them_on_drop() {
+        let path = {
+            let file = SecureTempFile::new().unwrap();
+            fs::write(file.path(), b"secret").unwrap();
+            assert_eq!(fs::metadata(file.path()).unwrap().permissions().mode() & 0o777, 0o600);
+            file.path().to_owned()
+        };
+        assert!(!path.exists());
+        assert!(LIVE_FILES.lock().unwrap().iter().all(|p| p != &path));
+    }
+
+    #[test]
+    fn it_wipes_all_files_within_temporary_directories() {
+        let path = {
+            let dir = SecureTempDir::new().unwrap();
+            dir.create_file(Path::new("a/b/secret"))
+                .unwrap()
+                .write_all(b"secret")
+                .unwrap();
+            assert_eq!(fs::metadata(dir.path()).unwrap().permissions().mode() & 0o777, 0o700);
+            dir.path().to_owned()
+        };
+        assert!(!path.exists());
+    }
+}
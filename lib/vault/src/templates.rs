@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use base::Vault;
+use failure::{Error, ResultExt};
+
+impl Vault {
+    /// The templates directory of `partition`, or the one of the leader if the partition has none.
+    pub fn templates_dir(&self, partition: &Vault) -> Result<PathBuf, Error> {
+        partition
+            .templates
+            .as_ref()
+            .map(|p| partition.absolute_path(p))
+            .or_else(|| self.templates.as_ref().map(|p| self.absolute_path(p)))
+            .ok_or_else(|| {
+                format_err!(
+                    "The vault at '{}' does not have a templates directory configured.",
+                    self.vault_path_for_display()
+                )
+            })
+    }
+
+    /// Read the template called `name`, which is a file in the templates directory, with or without its extension.
+    pub fn read_template(&self, partition: &Vault, name: &str) -> Result<Vec<u8>, Error> {
+        let is_file_name = match Path::new(name).components().collect::<Vec<_>>().as_slice() {
+            [Component::Normal(_)] => !name.contains('/'),
+            _ => false,
+        };
+        if !is_file_name {
+            bail!(
+                "Invalid template name '{}': it must be the name of a file in the templates directory.",
+                name
+            )
+        }
+        let dir = self.templates_dir(partition)?;
+        let exact_match = dir.join(name);
+        let path = if exact_match.is_file() {
+            exact_match
+        } else {
+            let mut candidates = fs::read_dir(&dir)
+                .context(format!("Could not read templates directory at '{}'.", dir.display()))?
+                .filter_map(Result::ok)
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && p.file_stem().map_or(false, |s| s == name))
+                .collect::<Vec<_>>();
+            candidates.sort();
+            match candidates.len() {
+                0 => bail!("There is no template named '{}' in '{}'.", name, dir.display()),
+                1 => candidates.pop().expect("one candidate"),
+                _ => bail!(
+                    "The template name '{}' is ambiguous. Please use one of {}.",
+                    name,
+                    candidates
+                        .iter()
+                        .filter_map(|p| p.file_name())
+                        .map(|f| format!("'{}'", f.to_string_lossy()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }
+        };
+        Ok(fs::read(&path).context(format!("Could not read template at '{}'.", path.display()))?)
+    }
+}
+
+#[cfg(test)]
+mod tests_templates {
+    use super::*;
+    use mktemp::Temp;
+
+    #[test]
+    fn it_finds_templates_with_and_without_extension() {
+        let dir = Temp::new_dir().unwrap();
+        fs::write(dir.as_ref().join("database.yml"), b"host:\nuser:\npassword:\n").unwrap();
+        let vault = Vault {
+            templates: Some(dir.to_path_buf()),
+            ..Default::default()
+        };
+        assert_eq!(
+            vault.read_template(&vault, "database").unwrap(),
+            vault.read_template(&vault, "database.yml").unwrap()
+        );
+        assert!(vault.read_template(&vault, "other").is_err());
+        assert!(Vault::default().read_template(&Vault::default(), "database").is_err());
+    }
+
+    #[test]
+    fn it_only_reads_templates_within_the_templates_directory() {
+        let tmp = Temp::new_dir().unwrap();
+        let dir = tmp.to_path_buf().join("templates");
+        fs::create_dir(&dir).unwrap();
+        fs::write(tmp.to_path_buf().join("secret"), b"not a template").unwrap();
+        let vault = Vault {
+            templates: Some(dir.clone()),
+            ..Default::default()
+        };
+        let absolute = tmp.to_path_buf().join("secret");
+        for name in &["../secret", "sub/../../secret", absolute.to_str().unwrap(), "..", ".", ""] {
+            let err = vault.read_template(&vault, name).unwrap_err();
+            assert!(format!("{}", err).starts_with("Invalid template name"), "{}", name);
+        }
+    }
+}
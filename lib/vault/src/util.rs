@@ -7,10 +7,10 @@ use std::fmt;
 use itertools::{join, Itertools};
 use failure::{self, err_msg, Error, ResultExt};
 use gpgme;
-use std::ffi::OsStr;
 use std::process::Command;
 use std::process::Stdio;
 use TrustModel;
+use tempfile::SecureTempFile;
 
 pub fn strip_ext(p: &Path) -> PathBuf {
     let mut p = p.to_owned();
@@ -184,24 +184,29 @@ impl Drop for ResetCWD {
     }
 }
 
-pub fn run_editor(editor: &OsStr, path_to_edit: &Path) -> Result<(), Error> {
-    let mut running_program = Command::new(editor)
-        .arg(path_to_edit)
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .spawn()
-        .with_context(|_| format!("Failed to start editor program at '{}'.", editor.to_string_lossy()))?;
-    let status = running_program
-        .wait()
-        .with_context(|_| "Failed to wait for editor to exit.")?;
-    if !status.success() {
-        return Err(format_err!(
-            "Editor '{}' failed. Edit aborted.",
-            editor.to_string_lossy()
-        ));
-    }
-    Ok(())
+/// Merge `ours` and `theirs`, which both derive from `base`, using `git merge-file`.
+/// Returns `None` if there were conflicts or if git is not available.
+pub fn merge_three_way(base: &[u8], ours: &[u8], theirs: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+    let files = [ours, base, theirs]
+        .iter()
+        .map(|content| {
+            let file = SecureTempFile::new()?;
+            write_at(file.path())
+                .and_then(|mut f| f.write_all(content))
+                .context("Could not write temporary file for merging.")?;
+            Ok(file)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let result = Command::new("git")
+        .args(&["merge-file", "--stdout", "--quiet"])
+        .args(files.iter().map(SecureTempFile::path))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+    Ok(match result {
+        Ok(ref output) if output.status.code() == Some(0) => Some(output.stdout.clone()),
+        _ => None,
+    })
 }
 
 pub fn print_causes<E, W>(e: E, mut w: W)
@@ -229,9 +234,27 @@ pub fn flags_for_model(model: &TrustModel) -> gpgme::EncryptFlags {
     flags.set(
         gpgme::EncryptFlags::ALWAYS_TRUST,
         match *model {
-            TrustModel::Always => true,
+            // Keys of vault members are trusted only after verifying their certifications by trusted members.
+            TrustModel::Always | TrustModel::VaultMembers => true,
             TrustModel::GpgWebOfTrust => false,
         },
     );
     flags
 }
+
+#[cfg(test)]
+mod tests_merge {
+    use super::*;
+
+    #[test]
+    fn it_merges_independent_changes_and_detects_conflicts() {
+        let base = b"one\ntwo\nthree\nfour\n";
+        let ours = b"ONE\ntwo\nthree\nfour\n";
+        let theirs = b"one\ntwo\nthree\nFOUR\n";
+        assert_eq!(
+            merge_three_way(base, ours, theirs).unwrap(),
+            Some(b"ONE\ntwo\nthree\nFOUR\n".to_vec())
+        );
+        assert_eq!(merge_three_way(base, ours, b"uno\ntwo\nthree\nfour\n").unwrap(), None);
+    }
+}
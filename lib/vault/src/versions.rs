@@ -0,0 +1,259 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use base::Vault;
+use chrono::{Local, TimeZone};
+use failure::{Error, ResultExt};
+use error::DecryptionError;
+use gpgme;
+use spec::{gpg_output_filename, Destination, SpecSourceType, VaultSpec, WriteMode};
+use util::{new_context, strip_ext};
+
+/// The directory within the secrets directory of a vault which holds previous versions of resources.
+pub const VERSIONS_DIR: &str = ".versions";
+
+/// A previous ciphertext of a resource. The newest version has index 1.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Version {
+    pub index: usize,
+    pub path: PathBuf,
+    pub written_at: Duration,
+}
+
+fn versions_dir_of(secrets_dir: &Path, encrypted_path: &Path) -> Option<PathBuf> {
+    let resource = encrypted_path.strip_prefix(secrets_dir).ok()?;
+    // Without the extension, as the versions directory must not look like a resource
+    Some(secrets_dir.join(VERSIONS_DIR).join(strip_ext(resource)))
+}
+
+/// Parse a version file name like '<secs>.<nanos>' or '<secs>.<nanos>-<n>' into the time it was written at
+/// and the sequence number, which distinguishes versions written at the same time.
+fn parse_version_name(name: &str) -> Option<(Duration, usize)> {
+    let mut tokens = name.splitn(2, '.');
+    let secs = tokens.next()?.parse().ok()?;
+    let mut tokens = tokens.next().unwrap_or("0").splitn(2, '-');
+    let nanos = tokens.next()?.parse().ok()?;
+    let sequence = tokens.next().map_or(Some(0), |n| n.parse().ok())?;
+    Some((Duration::new(secs, nanos), sequence))
+}
+
+fn versions_in(dir: &Path) -> Result<Vec<Version>, Error> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut versions = fs::read_dir(dir)
+        .context(format!("Could not read versions directory at '{}'.", dir.display()))?
+        .filter_map(Result::ok)
+        .filter_map(|e| {
+            let path = e.path();
+            let (written_at, sequence) = parse_version_name(path.file_name()?.to_str()?)?;
+            Some((
+                sequence,
+                Version {
+                    index: 0,
+                    path,
+                    written_at,
+                },
+            ))
+        })
+        .collect::<Vec<_>>();
+    versions.sort_by(|a, b| (b.1.written_at, b.0).cmp(&(a.1.written_at, a.0)));
+    Ok(versions
+        .into_iter()
+        .enumerate()
+        .map(|(index, (_, version))| Version {
+            index: index + 1,
+            ..version
+        })
+        .collect())
+}
+
+/// Copy the ciphertext at `encrypted_path` into the versions directory, and remove all but the `keep` newest versions.
+/// Versions are named after the modification time of the ciphertext, with a sequence number appended if another
+/// version was written at the same time, as file systems might not track time precisely.
+pub fn keep_version(secrets_dir: &Path, encrypted_path: &Path, keep: usize) -> Result<(), Error> {
+    if keep == 0 || !encrypted_path.is_file() {
+        return Ok(());
+    }
+    let dir = match versions_dir_of(secrets_dir, encrypted_path) {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+    let written_at = fs::metadata(encrypted_path)
+        .and_then(|m| m.modified())
+        .context(format!("Could not obtain modification time of '{}'.", encrypted_path.display()))?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    fs::create_dir_all(&dir).context(format!("Could not create versions directory at '{}'.", dir.display()))?;
+    let content = fs::read(encrypted_path).context(format!("Could not read '{}'.", encrypted_path.display()))?;
+    let name = format!("{}.{:09}", written_at.as_secs(), written_at.subsec_nanos());
+    let mut version_path = dir.join(&name);
+    for sequence in 1.. {
+        match fs::read(&version_path) {
+            Ok(ref existing) if *existing == content => break,
+            Ok(_) => version_path = dir.join(format!("{}-{}", name, sequence)),
+            Err(_) => {
+                fs::write(&version_path, &content).context(format!(
+                    "Could not keep previous version of '{}' at '{}'.",
+                    encrypted_path.display(),
+                    version_path.display()
+                ))?;
+                break;
+            }
+        }
+    }
+    for version in versions_in(&dir)?.into_iter().skip(keep) {
+        fs::remove_file(&version.path).context(format!(
+            "Could not remove outdated version at '{}'.",
+            version.path.display()
+        ))?;
+    }
+    Ok(())
+}
+
+fn format_time(time: Duration) -> String {
+    Local
+        .timestamp(time.as_secs() as i64, time.subsec_nanos())
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+impl Vault {
+    pub fn find_keep_versions(&self, partition: &Vault) -> usize {
+        partition.keep_versions.or(self.keep_versions).unwrap_or(0)
+    }
+
+    /// The secrets directory of the partition owning `path`, and the path to its encrypted file.
+    fn encrypted_path_of(&self, path: &Path) -> Result<(PathBuf, PathBuf), Error> {
+        let (partition, path) = self.partition_by_owned_path(path.to_owned())?;
+        let secrets_dir = partition.secrets_path();
+        let path = match path.extension() {
+            Some(ext) if ext == "gpg" => path,
+            _ => gpg_output_filename(&path)?,
+        };
+        let encrypted_path = secrets_dir.join(path);
+        Ok((secrets_dir, encrypted_path))
+    }
+
+    pub fn versions(&self, path: &Path) -> Result<Vec<Version>, Error> {
+        let (secrets_dir, encrypted_path) = self.encrypted_path_of(path)?;
+        match versions_dir_of(&secrets_dir, &encrypted_path) {
+            Some(dir) => versions_in(&dir),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn print_history(&self, path: &Path, output: &mut Write) -> Result<(), Error> {
+        let versions = self.versions(path)?;
+        if versions.is_empty() {
+            bail!("There are no previous versions of '{}'.", path.display())
+        }
+        for version in versions {
+            writeln!(output, "{}\t{}", version.index, format_time(version.written_at))?;
+        }
+        Ok(())
+    }
+
+    /// Decrypt the version with `index` of the resource at `path`, and encrypt it for the current recipients.
+    pub fn restore(&self, path: &Path, index: usize, output: &mut Write, error: &mut Write) -> Result<(), Error> {
+        let version = self.versions(path)?
+            .into_iter()
+            .find(|v| v.index == index)
+            .ok_or_else(|| format_err!("There is no version {} of '{}'.", index, path.display()))?;
+        let mut content = Vec::new();
+        {
+            let mut input = File::open(&version.path).context(format!(
+                "Could not read version at '{}'.",
+                version.path.display()
+            ))?;
+            new_context()?
+                .decrypt(&mut input, &mut content)
+                .map_err(|e: gpgme::Error| DecryptionError::caused_by(e, "Failed to decrypt data."))?;
+        }
+        let resource = match path.extension() {
+            Some(ext) if ext == "gpg" => strip_ext(path),
+            _ => path.to_owned(),
+        };
+        self.encrypt(
+            &[
+                VaultSpec {
+                    src: SpecSourceType::Buffer(content),
+                    dst: resource,
+                },
+            ],
+            WriteMode::AllowOverwrite,
+            Destination::ReolveAndAppendGpg,
+            &mut io::sink(),
+            error,
+        ).context(format!("Failed to encrypt version {} of '{}'.", index, path.display()))?;
+        writeln!(
+            output,
+            "Restored version {} of '{}' from {}.",
+            index,
+            path.display(),
+            format_time(version.written_at)
+        ).ok();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_versions {
+    use super::*;
+    use mktemp::Temp;
+
+    #[test]
+    fn it_keeps_only_the_configured_amount_of_versions() {
+        let tmp = Temp::new_dir().unwrap();
+        let secrets_dir = tmp.to_path_buf();
+        let resource = secrets_dir.join("db/password.gpg");
+        fs::create_dir_all(resource.parent().unwrap()).unwrap();
+        let dir = versions_dir_of(&secrets_dir, &resource).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        for name in &["100.000000000", "300.000000000", "200.000000000"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        fs::write(&resource, b"current").unwrap();
+
+        keep_version(&secrets_dir, &resource, 2).unwrap();
+
+        let versions = versions_in(&dir).unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].index, 1);
+        assert_eq!(fs::read(&versions[0].path).unwrap(), b"current");
+        assert_eq!(versions[1].written_at, Duration::new(300, 0));
+    }
+
+    #[test]
+    fn it_keeps_distinct_versions_written_at_the_same_time() {
+        let tmp = Temp::new_dir().unwrap();
+        let secrets_dir = tmp.to_path_buf();
+        let resource = secrets_dir.join("password.gpg");
+        fs::write(&resource, b"current").unwrap();
+        let written_at = fs::metadata(&resource).unwrap().modified().unwrap();
+        let written_at = written_at.duration_since(UNIX_EPOCH).unwrap();
+        let dir = versions_dir_of(&secrets_dir, &resource).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(format!("{}.{:09}", written_at.as_secs(), written_at.subsec_nanos())),
+            b"older",
+        ).unwrap();
+
+        keep_version(&secrets_dir, &resource, 5).unwrap();
+        keep_version(&secrets_dir, &resource, 5).unwrap();
+
+        let versions = versions_in(&dir).unwrap();
+        assert_eq!(versions.len(), 2, "the same version is only kept once");
+        assert_eq!(fs::read(&versions[0].path).unwrap(), b"current");
+        assert_eq!(fs::read(&versions[1].path).unwrap(), b"older");
+    }
+
+    #[test]
+    fn it_parses_version_names_with_and_without_sequence_number() {
+        assert_eq!(parse_version_name("100.000000005"), Some((Duration::new(100, 5), 0)));
+        assert_eq!(parse_version_name("100.000000005-2"), Some((Duration::new(100, 5), 2)));
+        assert_eq!(parse_version_name("not-a-version"), None);
+    }
+}
@@ -40,3 +40,27 @@ gpg_keys: ~
 recipients: ".gpg-id""#
     );
 }
+
+#[test]
+fn vault_members_trust_model_serde() {
+    let mut v = Vault::default();
+    v.trust_model = Some("vault-members".parse().unwrap());
+    v.required_certifications = Some(2);
+    let res = serde_yaml::to_string(&v).unwrap();
+
+    assert_eq!(
+        res,
+        r#"---
+name: ~
+auto_import: true
+trust_model: "vault-members"
+secrets: "."
+gpg_keys: ~
+recipients: ".gpg-id"
+required_certifications: 2"#
+    );
+    assert_eq!(
+        serde_yaml::from_str::<Vault>(&res).unwrap().trust_model,
+        Some(TrustModel::VaultMembers)
+    );
+}
@@ -1,6 +1,7 @@
 use clap::{App, Arg};
 use clap::AppSettings;
-use std::env;
+use vault::Editor;
+use cli::util::output_formats;
 
 fn mk_help(kind: &str, prefix: &str) -> String {
     format!(
@@ -12,7 +13,7 @@ fn mk_help(kind: &str, prefix: &str) -> String {
 }
 
 lazy_static! {
-    static ref EDITOR: Result<String, env::VarError> = env::var("EDITOR");
+    static ref EDITOR: Option<String> = Editor::command_from_env();
     static ref PARTITION_HELP: String = mk_help("partition", "");
     static ref VAULT_HELP: String = mk_help(
         "vault",
@@ -79,6 +80,18 @@ pub fn new<'a, 'b>() -> App<'a, 'b> {
                      that you set an explicit secrets directory.",
                 ),
         )
+        .arg(
+            Arg::with_name("key-policy")
+                .long("key-policy")
+                .required(false)
+                .takes_value(true)
+                .value_name("file")
+                .help(
+                    "A YAML file with requirements for the keys of all recipients, which is stored as 'key_policy' \
+                     in the vault description file. It may contain 'algorithms', a list of allowed algorithms like \
+                     'rsa' or 'ed25519', 'min_rsa_bits', 'max_expiry_years' and 'forbid_sha1_self_signatures'.",
+                ),
+        )
         .arg(
             Arg::with_name("no-auto-import")
                 .long("no-auto-import")
@@ -94,12 +107,17 @@ pub fn new<'a, 'b>() -> App<'a, 'b> {
                 .takes_value(true)
                 .value_name("model")
                 .default_value("always")
-                .possible_values(&["web-of-trust", "always"])
+                .possible_values(&["web-of-trust", "always", "vault-members"])
                 .help(
                     "The model by which keys to encrypt for are verified to truly belong to the person. If unset, it defaults to 'always'.\
                      'always': whenever a key has been added to the vault, it is trusted without your intervention. \
                      'web-of-trust': the standard GPG web of trust with default rules. In the most simple case, you will \
-                     need to sign a key prior to be able to encrypt for it.",
+                     need to sign a key prior to be able to encrypt for it. \
+                     'vault-members': a key must be certified by trusted recipients of the same partition, \
+                     as many as 'required_certifications' in the vault description file, which defaults to 1, \
+                     or by all other recipients if there are fewer. Trusted are your own keys, which need no \
+                     certification, the signed recipients you accepted last, and keys certified by trusted ones. \
+                     Certifications are shared through the gpg keys directory.",
                 ),
         )
         .arg(
@@ -129,7 +147,40 @@ pub fn new<'a, 'b>() -> App<'a, 'b> {
         )
         .arg(optional_gpg_key_id(gpg_key_id.clone()));
 
-    let list = App::new("list").alias("ls").about("List the vault's content.");
+    let list = App::new("list")
+        .alias("ls")
+        .about("List the vault's content.")
+        .arg(Arg::with_name("meta").long("meta").short("m").required(false).help(
+            "Show the metadata of each resource, as set by 'vault meta set'.",
+        ))
+        .arg(
+            Arg::with_name("where")
+                .long("where")
+                .short("w")
+                .required(false)
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("name=value")
+                .help(
+                    "Only list resources whose metadata field 'name' is 'value'. \
+                     It can be specified multiple times, and all conditions must match.",
+                ),
+        )
+        .arg(
+            Arg::with_name("tag")
+                .long("tag")
+                .short("t")
+                .required(false)
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("tag")
+                .help(
+                    "Only list resources carrying the given tag, as set by 'vault tag'. \
+                     It can be specified multiple times, and all tags must be present.",
+                ),
+        );
     let resource_path = Arg::with_name("path")
         .required(true)
         .multiple(false)
@@ -163,17 +214,205 @@ pub fn new<'a, 'b>() -> App<'a, 'b> {
                 .takes_value(true)
                 .default_value(EDITOR.as_ref().map(String::as_str).unwrap_or("vim"))
                 .help(
-                    "The path to your editor program. It receives the decrypted content as first \
-                     argument and is expected to write the changes back to that file before quitting.",
+                    "The command line of your editor program, like 'code --wait'. It receives the decrypted \
+                     content as last argument and is expected to write the changes back to that file before \
+                     quitting. Defaults to $VISUAL or $EDITOR.",
                 ),
         )
-        .arg(resource_path.clone())
+        .arg(
+            Arg::with_name("template")
+                .long("template")
+                .short("t")
+                .required(false)
+                .takes_value(true)
+                .value_name("name")
+                .conflicts_with("no-create")
+                .help(
+                    "The name of a file in the templates directory of the vault, with or without its extension. \
+                     It is used as initial content of the resource to be created.",
+                ),
+        )
+        .arg(Arg::with_name("wait").long("wait").short("w").required(false).help(
+            "If set, we will wait for you to confirm that you are done editing before re-encrypting the content. \
+             Useful for editors which return immediately. Can also be enabled by setting SY_EDITOR_WAIT.",
+        ))
+        .arg(resource_path.clone().multiple(true).help(
+            "The resource(s) to edit, as a vault-relative path as displayed by 'vault list', \
+             a vault-relative path with the '.gpg' suffix, an absolute path, or a quoted \
+             glob pattern like 'db/*'.",
+        ))
         .about(
-            "Edit a resource. This will decrypt the resource to \
-             a temporary file, open up the $EDITOR you have specified, and re-encrypt the \
+            "Edit one or more resources. This will decrypt the resources to \
+             temporary files, open up the editor you have specified with all of them, and re-encrypt the \
              changed content before deleting it on disk.",
         );
-    let show_resource = App::new("show").about("Decrypt a resource").arg(resource_path.clone());
+    let show_resource = App::new("show")
+        .about("Decrypt a resource")
+        .arg(
+            Arg::with_name("pointer")
+                .long("pointer")
+                .short("p")
+                .required(false)
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("pointer")
+                .help(
+                    "Display only the value at the given pointer within the structured (YAML or JSON) resource, \
+                     using the same syntax as 'extract', like 'a.b' or '/a/b'. \
+                     It can be specified multiple times.",
+                ),
+        )
+        .arg(
+            Arg::with_name("field")
+                .long("field")
+                .short("f")
+                .required(false)
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("name")
+                .conflicts_with("pointer")
+                .help(
+                    "Display only the given field of a resource stored like 'pass' does it: \
+                     the first line is the password, followed by lines like 'name: value'. \
+                     The 'password' field refers to the first line. \
+                     It can be specified multiple times.",
+                ),
+        )
+        .arg(resource_path.clone());
+    let set_resource = App::new("set")
+        .about(
+            "Set values within a structured (YAML or JSON) resource and re-encrypt it. \
+             The document keeps its format, but comments in YAML documents are not retained.",
+        )
+        .arg(resource_path.clone())
+        .arg(
+            Arg::with_name("assignment")
+                .required(true)
+                .multiple(true)
+                .takes_value(true)
+                .value_name("pointer=value")
+                .help(
+                    "The pointer to the value to set or insert, like 'a.b' or '/a/b', followed by '=' \
+                     and the value, which is stored as string. Use ':=' instead of '=' to provide a JSON value \
+                     like 5432, true or [1, 2], which is stored with its type.",
+                ),
+        );
+    let otp_resource = App::new("otp")
+        .about(
+            "Display the current time-based one-time password (TOTP) for an 'otpauth://totp/...' URI \
+             stored in a resource. The remaining validity is written to standard error.",
+        )
+        .arg(
+            Arg::with_name("field")
+                .long("field")
+                .short("f")
+                .required(false)
+                .takes_value(true)
+                .value_name("name")
+                .help(
+                    "The name of the field holding the URI in a resource stored like 'pass' does it. \
+                     If unset, the first line starting with 'otpauth://totp/' is used.",
+                ),
+        )
+        .arg(resource_path.clone());
+    let stale = App::new("stale")
+        .about(
+            "List resources which are due for rotation, grouped by partition. These are resources past the \
+             'rotate_after' date in their metadata, or which were not modified for the given amount of days. \
+             The last modification is taken from the 'updated' field of their metadata, then from git if \
+             possible, or from the file system otherwise. Re-encrypting resources for other recipients \
+             does not count as modification. Exits with a non-zero code if there is at least one such resource.",
+        )
+        .arg(
+            Arg::with_name("days")
+                .long("days")
+                .short("d")
+                .required(false)
+                .takes_value(true)
+                .value_name("days")
+                .help("Also list resources which were not modified for more than the given amount of days."),
+        );
+    let meta_get = App::new("get")
+        .about("Show the metadata of a resource, or only the values of the given fields.")
+        .arg(resource_path.clone())
+        .arg(
+            Arg::with_name("field")
+                .required(false)
+                .multiple(true)
+                .takes_value(true)
+                .value_name("name"),
+        );
+    let meta_set = App::new("set")
+        .about(
+            "Set metadata fields of a resource. Useful fields are 'description', 'owner' and 'rotate_after', \
+             the latter being a date like '2018-12-31'. The 'created' and 'updated' fields are maintained \
+             automatically. Metadata is stored in clear unless 'encrypt_metadata' is set in the vault \
+             description file.",
+        )
+        .arg(resource_path.clone())
+        .arg(
+            Arg::with_name("assignment")
+                .required(true)
+                .multiple(true)
+                .takes_value(true)
+                .value_name("name=value")
+                .help("The field to set, followed by '=' and its value. An empty value removes the field."),
+        );
+    let tag = App::new("tag")
+        .about(
+            "Add or remove tags of a resource and show the tags it carries afterwards. \
+             Tags are stored in clear in the '.tags.yml' file of the secrets directory.",
+        )
+        .arg(resource_path.clone())
+        .arg(
+            Arg::with_name("remove")
+                .long("remove")
+                .short("r")
+                .required(false)
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true)
+                .value_name("tag")
+                .help("A tag to remove. It can be specified multiple times."),
+        )
+        .arg(
+            Arg::with_name("tag")
+                .required(false)
+                .multiple(true)
+                .takes_value(true)
+                .value_name("tag")
+                .help("A tag to add."),
+        );
+    let meta = App::new("meta")
+        .setting(AppSettings::VersionlessSubcommands)
+        .setting(AppSettings::DeriveDisplayOrder)
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .about("Interact with the metadata of resources, like their owner or rotation date.")
+        .subcommand(meta_get)
+        .subcommand(meta_set);
+    let history_resource = App::new("history")
+        .about(
+            "List the previous versions of a resource, newest first, along with the time they were written. \
+             Versions are only kept if 'keep_versions' is set in the vault description file.",
+        )
+        .arg(resource_path.clone());
+    let restore_resource = App::new("restore")
+        .about(
+            "Replace a resource with one of its previous versions, as listed by 'vault history'. \
+             The version is encrypted for the current recipients.",
+        )
+        .arg(
+            Arg::with_name("version")
+                .long("version")
+                .short("v")
+                .required(true)
+                .takes_value(true)
+                .value_name("index")
+                .help("The index of the version to restore, with 1 being the most recent one."),
+        )
+        .arg(resource_path.clone());
     let spec = Arg::with_name("spec")
         .required(true)
         .multiple(false)
@@ -189,8 +428,128 @@ pub fn new<'a, 'b>() -> App<'a, 'b> {
              <dst> should be vault-relative paths, whereas <src> must point to a readable file \
              and can be empty to read from standard input, such as in ':<dst>'.\
              If standard input is a TTY, it will open the editor as defined by the \
-             EDITOR environment variable.",
-        ));
+             VISUAL or EDITOR environment variables.",
+        ))
+        .arg(
+            Arg::with_name("field")
+                .long("field")
+                .short("f")
+                .required(false)
+                .multiple(true)
+                .takes_value(true)
+                .number_of_values(1)
+                .value_name("name=value")
+                .help(
+                    "Store the resource like 'pass' does it, with the password on the first line followed by \
+                     lines like 'name: value'. The input becomes the record the given fields are added to, \
+                     and 'password=<value>' sets the first line. \
+                     If 'password' is given and standard input is a TTY, no input will be read. \
+                     It can be specified multiple times.",
+                ),
+        )
+        .arg(Arg::with_name("prompt").long("prompt").short("p").required(false).help(
+            "Instead of opening an editor, ask for the secret of every spec reading from standard input, \
+             like ':<dst>'. The secret is not shown while typing and has to be entered twice.",
+        ))
+        .arg(
+            Arg::with_name("multiline")
+                .long("multiline")
+                .short("m")
+                .required(false)
+                .requires("prompt")
+                .help("When prompting, read secrets spanning multiple lines until Ctrl-D is pressed."),
+        );
+    let generate_resource = App::new("generate")
+        .alias("gen")
+        .about(
+            "Generate a random secret and store it as a new resource in the vault. \
+             The secret is encrypted right away and never written to disk in plain text. \
+             By default, a password made of lower- and uppercase letters and digits is generated.",
+        )
+        .arg(
+            Arg::with_name("length")
+                .long("length")
+                .short("l")
+                .required(false)
+                .takes_value(true)
+                .value_name("characters")
+                .default_value("32")
+                .help("The amount of characters of the generated password."),
+        )
+        .arg(
+            Arg::with_name("classes")
+                .long("chars")
+                .required(false)
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .value_name("class")
+                .possible_values(&["lower", "upper", "digit", "symbol", "alpha", "alnum", "hex"])
+                .help(
+                    "The character classes the generated password is made of. \
+                     It will contain at least one character of each class.",
+                ),
+        )
+        .arg(Arg::with_name("no-ambiguous").long("no-ambiguous").required(false).help(
+            "If set, characters which are easily confused with each other, like '0' and 'O' or 'l' and '1', \
+             will not be used.",
+        ))
+        .arg(
+            Arg::with_name("words")
+                .long("words")
+                .required(false)
+                .takes_value(true)
+                .value_name("count")
+                .conflicts_with_all(&["classes", "hex", "base64", "template"])
+                .help("Generate a passphrase made of the given amount of randomly chosen words."),
+        )
+        .arg(
+            Arg::with_name("separator")
+                .long("separator")
+                .required(false)
+                .takes_value(true)
+                .value_name("string")
+                .default_value("-")
+                .help("The string to put between the words of a passphrase."),
+        )
+        .arg(
+            Arg::with_name("hex")
+                .long("hex")
+                .required(false)
+                .takes_value(true)
+                .value_name("bytes")
+                .conflicts_with_all(&["classes", "base64", "template"])
+                .help("Generate a token of the given amount of random bytes, encoded as hexadecimal string."),
+        )
+        .arg(
+            Arg::with_name("base64")
+                .long("base64")
+                .required(false)
+                .takes_value(true)
+                .value_name("bytes")
+                .conflicts_with_all(&["classes", "template"])
+                .help("Generate a token of the given amount of random bytes, encoded as base64 string."),
+        )
+        .arg(
+            Arg::with_name("template")
+                .long("template")
+                .short("t")
+                .required(false)
+                .takes_value(true)
+                .value_name("template")
+                .conflicts_with("classes")
+                .help(
+                    "Generate a secret following the given template, like '{{16:alnum}}-{{4:digit}}'. \
+                     Each placeholder is replaced by the given amount of random characters of the given class.",
+                ),
+        )
+        .arg(Arg::with_name("print").long("print").short("p").required(false).help(
+            "If set, the generated secret will be displayed once after it was stored in the vault.",
+        ))
+        .arg(Arg::with_name("force").long("force").short("f").required(false).help(
+            "If set, an existing resource at the given path will be overwritten with the new secret.",
+        ))
+        .arg(resource_path.clone().help("The vault-relative path at which to store the generated secret."));
     let remove_resource = App::new("remove")
         .alias("delete")
         .about("Delete a resource from the vault.")
@@ -240,6 +599,18 @@ pub fn new<'a, 'b>() -> App<'a, 'b> {
              You have used `gpg --sign-key <recipient>` or have set the owner trust to ultimate so that you \
              can encrypt for the recipient.",
         ))
+        .arg(
+            Arg::with_name("comment")
+                .long("comment")
+                .short("c")
+                .required(false)
+                .takes_value(true)
+                .value_name("text")
+                .help(
+                    "A comment to record next to the fingerprint of each added recipient in the recipients file, \
+                     like the name of the person or a ticket number.",
+                ),
+        )
         .arg(gpg_key_id.clone().required(true))
         .about(
             "Add a new recipient. This will re-encrypt all the vaults content.\
@@ -281,7 +652,216 @@ pub fn new<'a, 'b>() -> App<'a, 'b> {
         .arg(gpg_key_id.clone().required(true));
     let list_recipient = App::new("list")
         .alias("ls")
-        .about("List the vaults recipients as identified by the recipients file.");
+        .about(
+            "List the vaults recipients as identified by the recipients file, along with the algorithm, \
+             creation and expiry date of their keys. Revoked keys and keys which cannot encrypt are marked.",
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .required(false)
+                .value_name("mode")
+                .possible_values(output_formats())
+                .case_insensitive(true)
+                .help("Print the recipients of each partition in the given structured format instead."),
+        );
+    let check_recipients = App::new("check")
+        .about(
+            "Check the keys of all recipients of all partitions and list those which are expired, revoked, \
+             cannot encrypt, expire soon or violate the 'key_policy' of the vault. \
+             Exits with a non-zero code if there is at least one such key.",
+        )
+        .arg(
+            Arg::with_name("warn-days")
+                .long("warn-days")
+                .short("w")
+                .required(false)
+                .takes_value(true)
+                .default_value("30")
+                .value_name("days")
+                .help("Also list keys which expire within the given amount of days."),
+        );
+    let refresh_recipients = App::new("refresh")
+        .about(
+            "Export the public key of every recipient of all partitions from your gpg keyring into the \
+             gpg keys directory if it changed there, for example because its expiry was extended. \
+             Keys already in the gpg keys directory are merged into your keyring first.",
+        )
+        .arg(
+            Arg::with_name("from")
+                .long("from")
+                .short("f")
+                .required(false)
+                .takes_value(true)
+                .value_name("file")
+                .help(
+                    "A file with updated public keys, as sent by a colleague, to import into your keyring first. \
+                     It must not contain keys of anyone but the recipients.",
+                ),
+        );
+    let replace_recipient = App::new("replace")
+        .about(
+            "Replace the key of a recipient by a new one in all partitions it is a recipient of, \
+             including the groups it is a member of, for example after it was rotated. \
+             Each affected partition is re-encrypted exactly once, \
+             and the gpg keys directory is updated accordingly. \
+             The new key must be in your gpg keyring or the gpg keys directory, and be trusted.",
+        )
+        .arg(
+            Arg::with_name("old-id")
+                .required(true)
+                .takes_value(true)
+                .value_name("old-id")
+                .help("The fingerprint or key id of the recipient to replace."),
+        )
+        .arg(
+            Arg::with_name("new-id")
+                .required(true)
+                .takes_value(true)
+                .value_name("new-id")
+                .help("The fingerprint, key id or user id of the key replacing it."),
+        );
+    let certify_recipients = App::new("certify")
+        .about(
+            "Certify the keys of recipients with your key, which must be a recipient itself, and export \
+             them along with your certification into the gpg keys directory. \
+             The 'vault-members' trust model only encrypts for keys certified by trusted recipients.",
+        )
+        .arg(gpg_key_id.clone().required(true))
+        .arg(
+            Arg::with_name("signing-key")
+                .long("signing-key")
+                .takes_value(true)
+                .required(false)
+                .help(
+                    "The userid or fingerprint of the key to certify with. \
+                     It must only be specified if you have access to multiple secret keys which are \
+                     also current recipients.",
+                ),
+        );
+    let sign_recipients = App::new("sign")
+        .about(
+            "Sign the recipients of a partition, including the members of its groups, with your key. \
+             Once signed, the recipients must stay signed, are verified before encrypting, and are signed \
+             again whenever they are changed. Changes must be signed by one of the recipients you accepted \
+             last, so someone who adds themselves without being one of them will be refused. \
+             Only sign recipients after making sure they are the ones you expect, see 'recipients list'.",
+        )
+        .arg(
+            Arg::with_name("partition")
+                .long("partition")
+                .short("p")
+                .required(false)
+                .value_name("partition")
+                .multiple(true)
+                .takes_value(true)
+                .help(
+                    "Identifies the partition whose recipients to sign, using its name or its secrets directory. \
+                     If unset, the naturally selected vault is used, see the --select flag.",
+                ),
+        )
+        .arg(
+            Arg::with_name("signing-key")
+                .long("signing-key")
+                .takes_value(true)
+                .required(false)
+                .help(
+                    "The userid or fingerprint of the key to sign with. \
+                     It must only be specified if you have access to multiple secret keys which are \
+                     also current recipients.",
+                ),
+        );
+    let request_recipient = App::new("request")
+        .about(
+            "Ask for access to the vault by exporting your public key into the 'pending' directory \
+             of the gpg keys directory. Commit and share it, so an existing recipient can approve it.",
+        )
+        .arg(
+            Arg::with_name("note")
+                .long("note")
+                .short("n")
+                .required(false)
+                .takes_value(true)
+                .value_name("text")
+                .help("A note for those approving the request, signed with your key."),
+        )
+        .arg(gpg_key_id.clone().help(
+            "The key-id of your secret key to request access for. Needed only if you have more than one.",
+        ));
+    let pending_recipients = App::new("pending").about(
+        "List all pending access requests, along with their notes. \
+         Notes which are not signed by the requesting key are marked.",
+    );
+    let approve_recipient = App::new("approve")
+        .about(
+            "Approve an access request by certifying its key with yours and adding it as recipient, \
+             re-encrypting the vault, and removing the request. \
+             Make sure you verified the fingerprint with the requester.",
+        )
+        .arg(
+            Arg::with_name("fingerprint")
+                .required(true)
+                .takes_value(true)
+                .value_name("fingerprint")
+                .help("The fingerprint, or its unique end, of the pending request to approve."),
+        )
+        .arg(
+            Arg::with_name("signing-key")
+                .long("signing-key")
+                .takes_value(true)
+                .required(false)
+                .help(
+                    "The userid or fingerprint of the key to certify with. \
+                     It must only be specified if you have access to multiple secret keys which are \
+                     also current recipients.",
+                ),
+        )
+        .arg(
+            Arg::with_name("partition")
+                .long("partition")
+                .alias("to")
+                .short("p")
+                .required(false)
+                .value_name("partition")
+                .multiple(true)
+                .takes_value(true)
+                .help(
+                    "Identifies the partition to add the recipient to, using its name or its secrets directory. \
+                     If unset, the recipient will be added to naturally selected vault, see the --select flag.",
+                ),
+        );
+    let group_name = Arg::with_name("name")
+        .required(true)
+        .takes_value(true)
+        .value_name("name")
+        .help("The name of the group, as referenced by '@<name>' lines in recipients files.");
+    let group_add = App::new("add")
+        .alias("insert")
+        .about(
+            "Add recipients to a group, creating it if needed. \
+             All partitions referencing the group are re-encrypted.",
+        )
+        .arg(group_name.clone())
+        .arg(gpg_key_id.clone().required(true));
+    let group_remove = App::new("remove")
+        .about(
+            "Remove recipients from a group. \
+             All partitions referencing the group are re-encrypted.",
+        )
+        .arg(group_name.clone())
+        .arg(gpg_key_id.clone().required(true));
+    let group = App::new("group")
+        .setting(AppSettings::VersionlessSubcommands)
+        .setting(AppSettings::DeriveDisplayOrder)
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .about(
+            "Manage named groups of recipients, stored in the 'groups' section of the vault description file. \
+             A recipients file can list all members of a group with a line like '@<name>'.",
+        )
+        .subcommand(group_add)
+        .subcommand(group_remove);
     let recipients = App::new("recipients")
         .alias("recipient")
         .setting(AppSettings::VersionlessSubcommands)
@@ -290,6 +870,15 @@ pub fn new<'a, 'b>() -> App<'a, 'b> {
         .subcommand(init_recipient)
         .subcommand(add_recipient)
         .subcommand(list_recipient)
+        .subcommand(check_recipients)
+        .subcommand(refresh_recipients)
+        .subcommand(replace_recipient)
+        .subcommand(sign_recipients)
+        .subcommand(certify_recipients)
+        .subcommand(request_recipient)
+        .subcommand(pending_recipients)
+        .subcommand(approve_recipient)
+        .subcommand(group)
         .subcommand(remove_recipient);
     let add_partition = App::new("add")
         .alias("insert")
@@ -354,9 +943,17 @@ pub fn new<'a, 'b>() -> App<'a, 'b> {
         .about("Various commands to store and retrieve secrets and control who has access.")
         .subcommand(init)
         .subcommand(add_resource)
+        .subcommand(generate_resource)
         .subcommand(edit_resource)
         .subcommand(show_resource)
+        .subcommand(set_resource)
+        .subcommand(otp_resource)
+        .subcommand(meta)
+        .subcommand(tag)
+        .subcommand(history_resource)
+        .subcommand(restore_resource)
         .subcommand(list)
+        .subcommand(stale)
         .subcommand(remove_resource)
         .subcommand(recipients)
         .subcommand(partitions)
@@ -379,3 +976,19 @@ pub fn new<'a, 'b>() -> App<'a, 'b> {
                 .default_value("./sy-vault.yml"),
         )
 }
+
+#[cfg(test)]
+mod tests_vault {
+    use super::*;
+
+    #[test]
+    fn it_parses_tags_to_add_and_remove_even_if_they_look_like_flags() {
+        let matches = new()
+            .get_matches_from_safe(&["vault", "tag", "db/password", "staging", "-r", "h", "--remove", "V", "h"])
+            .unwrap();
+        let tag = matches.subcommand_matches("tag").unwrap();
+        assert_eq!(tag.value_of("path"), Some("db/password"));
+        assert_eq!(tag.values_of("tag").unwrap().collect::<Vec<_>>(), vec!["staging", "h"]);
+        assert_eq!(tag.values_of("remove").unwrap().collect::<Vec<_>>(), vec!["h", "V"]);
+    }
+}
@@ -1,19 +1,46 @@
 use std::path::PathBuf;
-use vault::{CreateMode, SigningMode, TrustModel, VaultSpec};
+use vault::{CreateMode, Editor, Generator, OutputMode, SigningMode, TagChange, TrustModel, VaultSpec, WriteMode};
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum Command {
     ResourceEdit {
-        editor: PathBuf,
+        editor: Editor,
         try_encrypt: bool,
-        spec: PathBuf,
+        specs: Vec<PathBuf>,
         mode: CreateMode,
+        template: Option<String>,
     },
     ResourceShow {
         spec: PathBuf,
+        pointers: Vec<String>,
+        fields: Vec<String>,
+    },
+    ResourceOtp {
+        spec: PathBuf,
+        field: Option<String>,
+    },
+    ResourceHistory {
+        spec: PathBuf,
+    },
+    ResourceRestore {
+        spec: PathBuf,
+        version: usize,
+    },
+    ResourceSet {
+        spec: PathBuf,
+        assignments: Vec<(String, String)>,
     },
     ResourceAdd {
         specs: Vec<VaultSpec>,
+        fields: Vec<(String, String)>,
+        prompt: bool,
+        multiline: bool,
+    },
+    ResourceGenerate {
+        spec: PathBuf,
+        generator: Generator,
+        print: bool,
+        mode: WriteMode,
     },
     ResourceRemove {
         specs: Vec<PathBuf>,
@@ -22,12 +49,51 @@ pub enum Command {
         name: Option<String>,
         trust_model: Option<TrustModel>,
         auto_import: Option<bool>,
+        key_policy: Option<PathBuf>,
         gpg_key_ids: Vec<String>,
         gpg_keys_dir: PathBuf,
         secrets: PathBuf,
         recipients_file: PathBuf,
     },
-    RecipientsList,
+    RecipientsList {
+        output_mode: Option<OutputMode>,
+    },
+    RecipientsCheck {
+        warn_days: u64,
+    },
+    RecipientsRefresh {
+        from: Option<PathBuf>,
+    },
+    RecipientsReplace {
+        old_id: String,
+        new_id: String,
+    },
+    RecipientsSign {
+        partitions: Vec<String>,
+        signing_key_id: Option<String>,
+    },
+    RecipientsCertify {
+        gpg_key_ids: Vec<String>,
+        signing_key_id: Option<String>,
+    },
+    RecipientsRequest {
+        gpg_key_ids: Vec<String>,
+        note: Option<String>,
+    },
+    RecipientsPending,
+    RecipientsApprove {
+        fingerprint: String,
+        signing_key_id: Option<String>,
+        partitions: Vec<String>,
+    },
+    RecipientsGroupAdd {
+        name: String,
+        gpg_key_ids: Vec<String>,
+    },
+    RecipientsGroupRemove {
+        name: String,
+        gpg_key_ids: Vec<String>,
+    },
     RecipientsInit {
         gpg_key_ids: Vec<String>,
     },
@@ -39,6 +105,7 @@ pub enum Command {
         partitions: Vec<String>,
         gpg_key_ids: Vec<String>,
         signing_key_id: Option<String>,
+        comment: Option<String>,
         sign: SigningMode,
     },
     PartitionsRemove {
@@ -50,7 +117,26 @@ pub enum Command {
         name: Option<String>,
         path: PathBuf,
     },
-    List,
+    List {
+        filters: Vec<(String, String)>,
+        tags: Vec<String>,
+        show_metadata: bool,
+    },
+    Tag {
+        spec: PathBuf,
+        changes: Vec<TagChange>,
+    },
+    Stale {
+        max_age_days: Option<u64>,
+    },
+    MetaGet {
+        spec: PathBuf,
+        fields: Vec<String>,
+    },
+    MetaSet {
+        spec: PathBuf,
+        assignments: Vec<(String, String)>,
+    },
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
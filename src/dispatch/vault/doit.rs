@@ -1,4 +1,4 @@
-use vault::{Vault, VaultExt};
+use vault::{prompt_for_secrets, KeyPolicy, Vault, VaultExt};
 use dispatch::vault::Context;
 use failure::Error;
 use std::io::Write;
@@ -30,30 +30,78 @@ fn inner_do_it(ctx: Context, output: &mut Write, error: &mut Write) -> Result<()
         RecipientsRemove {
             ref partitions,
             ref gpg_key_ids,
-        } => vault_from(&ctx)?.remove_recipients(gpg_key_ids, partitions, output),
+        } => vault_from(&ctx)?.remove_recipients(gpg_key_ids, partitions, output, error),
         RecipientsAdd {
             ref partitions,
             ref gpg_key_ids,
             ref sign,
             ref signing_key_id,
+            ref comment,
         } => vault_from(&ctx)?.add_recipients(
             gpg_key_ids,
             *sign,
             signing_key_id.as_ref().map(String::as_str),
             partitions,
+            comment.as_ref().map(String::as_str),
             output,
+            error,
         ),
-        RecipientsList => vault_from(&ctx)?.print_recipients(output, error),
+        RecipientsCheck { warn_days } => vault_from(&ctx)?.check_recipients(warn_days, output, error),
+        RecipientsRefresh { ref from } => {
+            vault_from(&ctx)?.refresh_recipients(from.as_ref().map(|f| f.as_path()), output)
+        }
+        RecipientsReplace {
+            ref old_id,
+            ref new_id,
+        } => vault_from(&ctx)?.replace_recipient(old_id, new_id, output, error),
+        RecipientsSign {
+            ref partitions,
+            ref signing_key_id,
+        } => vault_from(&ctx)?.sign_recipients_of(partitions, signing_key_id.as_ref().map(String::as_str), output),
+        RecipientsCertify {
+            ref gpg_key_ids,
+            ref signing_key_id,
+        } => vault_from(&ctx)?.certify_recipients(gpg_key_ids, signing_key_id.as_ref().map(String::as_str), output),
+        RecipientsRequest {
+            ref gpg_key_ids,
+            ref note,
+        } => vault_from(&ctx)?.request_access(gpg_key_ids, note.as_ref().map(String::as_str), output),
+        RecipientsPending => vault_from(&ctx)?.print_pending(output),
+        RecipientsApprove {
+            ref fingerprint,
+            ref signing_key_id,
+            ref partitions,
+        } => vault_from(&ctx)?.approve_request(
+            fingerprint,
+            signing_key_id.as_ref().map(String::as_str),
+            partitions,
+            output,
+            error,
+        ),
+        RecipientsGroupAdd {
+            ref name,
+            ref gpg_key_ids,
+        } => vault_from(&ctx)?.change_group(name, gpg_key_ids, true, output, error),
+        RecipientsGroupRemove {
+            ref name,
+            ref gpg_key_ids,
+        } => vault_from(&ctx)?.change_group(name, gpg_key_ids, false, output, error),
+        RecipientsList { output_mode } => vault_from(&ctx)?.print_recipients(output_mode, output, error),
         RecipientsInit { ref gpg_key_ids } => vault_from(&ctx)?.init_recipients(gpg_key_ids, output),
         Init {
             ref trust_model,
             ref auto_import,
+            ref key_policy,
             ref name,
             ref gpg_key_ids,
             ref gpg_keys_dir,
             ref recipients_file,
             ref secrets,
         } => {
+            let key_policy = match *key_policy {
+                Some(ref path) => Some(KeyPolicy::from_file(path)?),
+                None => None,
+            };
             Vault::init(
                 secrets,
                 gpg_key_ids,
@@ -63,25 +111,94 @@ fn inner_do_it(ctx: Context, output: &mut Write, error: &mut Write) -> Result<()
                 name.clone(),
                 trust_model.clone(),
                 auto_import.clone(),
+                key_policy,
                 output,
             )?;
             Ok(())
         }
         ResourceRemove { ref specs } => vault_from(&ctx)?.remove(specs, output),
-        ResourceAdd { ref specs } => vault_from(&ctx)?.encrypt(
-            specs,
-            WriteMode::RefuseOverwrite,
-            Destination::ReolveAndAppendGpg,
-            output,
-        ),
-        ResourceEdit {
+        ResourceAdd {
+            ref specs,
+            ref fields,
+            prompt,
+            multiline,
+        } => {
+            let vault = vault_from(&ctx)?;
+            let prompted_specs;
+            let specs = if prompt {
+                prompted_specs = prompt_for_secrets(specs, multiline, error)?;
+                &prompted_specs
+            } else {
+                specs
+            };
+            if fields.is_empty() {
+                vault.encrypt(
+                    specs,
+                    WriteMode::RefuseOverwrite,
+                    Destination::ReolveAndAppendGpg,
+                    output,
+                    error,
+                )
+            } else {
+                vault.encrypt_records(specs, fields, WriteMode::RefuseOverwrite, output, error)
+            }
+        }
+        ResourceGenerate {
             ref spec,
+            ref generator,
+            print,
+            mode,
+        } => vault_from(&ctx)?.generate(spec, generator, mode, print, output, error),
+        ResourceEdit {
+            ref specs,
             try_encrypt,
             ref editor,
             ref mode,
-        } => vault_from(&ctx)?.edit(spec, editor, mode, try_encrypt, output),
-        List => vault_from(&ctx)?.print_resources(output),
-        ResourceShow { ref spec } => vault_from(&ctx)?.decrypt(spec, output).map(|_| ()),
+            ref template,
+        } => vault_from(&ctx)?.edit_many(
+            specs,
+            editor,
+            mode,
+            template.as_ref().map(String::as_str),
+            try_encrypt,
+            output,
+            error,
+        ),
+        List {
+            ref filters,
+            ref tags,
+            show_metadata,
+        } => vault_from(&ctx)?.print_resources(filters, tags, show_metadata, output),
+        Tag { ref spec, ref changes } => vault_from(&ctx)?.tag(spec, changes, output),
+        Stale { max_age_days } => vault_from(&ctx)?.print_stale(max_age_days, output),
+        MetaGet { ref spec, ref fields } => vault_from(&ctx)?.print_metadata(spec, fields, output),
+        MetaSet {
+            ref spec,
+            ref assignments,
+        } => vault_from(&ctx)?.set_metadata(spec, assignments, output),
+        ResourceShow {
+            ref spec,
+            ref pointers,
+            ref fields,
+        } => {
+            let vault = vault_from(&ctx)?;
+            if !pointers.is_empty() {
+                vault.show_pointers(spec, pointers, output)
+            } else if !fields.is_empty() {
+                vault.show_fields(spec, fields, output)
+            } else {
+                vault.decrypt(spec, output).map(|_| ())
+            }
+        }
+        ResourceOtp { ref spec, ref field } => {
+            vault_from(&ctx)?.otp(spec, field.as_ref().map(String::as_str), output, error)
+        }
+        ResourceHistory { ref spec } => vault_from(&ctx)?.print_history(spec, output),
+        ResourceRestore { ref spec, version } => vault_from(&ctx)?.restore(spec, version, output, error),
+        ResourceSet {
+            ref spec,
+            ref assignments,
+        } => vault_from(&ctx)?.set_pointers(spec, assignments, output, error),
     }
 }
 
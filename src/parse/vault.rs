@@ -1,12 +1,12 @@
 use conv::TryInto;
-use failure::Error;
+use failure::{err_msg, Error};
 use clap::ArgMatches;
 
 use std::path::{Path, PathBuf};
 use std::convert::Into;
 
 use vault::error::{first_cause_of_type, DecryptionError};
-use vault::{CreateMode, SigningMode};
+use vault::{CharacterClass, CreateMode, Editor, Generator, OutputMode, SigningMode, TagChange, WriteMode};
 use dispatch::vault::{Command, Context};
 
 use super::util::{optional_args, required_arg, required_os_arg};
@@ -39,13 +39,115 @@ pub fn context_from(args: &ArgMatches) -> Result<Context, Error> {
     Ok(Context {
         vault_path: required_os_arg(args, "config-file")?,
         vault_selector: required_arg(args, "vault-selector")?,
-        command: Command::List,
+        command: Command::List {
+            filters: Vec::new(),
+            tags: Vec::new(),
+            show_metadata: false,
+        },
+    })
+}
+
+pub fn recipients_list(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::RecipientsList {
+            output_mode: value_t!(args, "output", OutputMode).ok(),
+        },
+        ..ctx
+    })
+}
+
+pub fn recipients_check(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::RecipientsCheck {
+            warn_days: required_arg(args, "warn-days")?,
+        },
+        ..ctx
+    })
+}
+
+pub fn recipients_refresh(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::RecipientsRefresh {
+            from: args.value_of_os("from").map(PathBuf::from),
+        },
+        ..ctx
+    })
+}
+
+pub fn recipients_replace(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::RecipientsReplace {
+            old_id: required_arg(args, "old-id")?,
+            new_id: required_arg(args, "new-id")?,
+        },
+        ..ctx
+    })
+}
+
+pub fn recipients_sign(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::RecipientsSign {
+            partitions: optional_args(args, "partition"),
+            signing_key_id: args.value_of("signing-key").map(ToOwned::to_owned),
+        },
+        ..ctx
+    })
+}
+
+pub fn recipients_certify(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::RecipientsCertify {
+            gpg_key_ids: optional_args(args, "gpg-key-id"),
+            signing_key_id: args.value_of("signing-key").map(ToOwned::to_owned),
+        },
+        ..ctx
     })
 }
 
-pub fn recipients_list(ctx: Context, _args: &ArgMatches) -> Result<Context, Error> {
+pub fn recipients_request(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
     Ok(Context {
-        command: Command::RecipientsList,
+        command: Command::RecipientsRequest {
+            gpg_key_ids: optional_args(args, "gpg-key-id"),
+            note: args.value_of("note").map(ToOwned::to_owned),
+        },
+        ..ctx
+    })
+}
+
+pub fn recipients_pending(ctx: Context, _args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::RecipientsPending,
+        ..ctx
+    })
+}
+
+pub fn recipients_approve(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::RecipientsApprove {
+            fingerprint: required_arg(args, "fingerprint")?,
+            signing_key_id: args.value_of("signing-key").map(ToOwned::to_owned),
+            partitions: optional_args(args, "partition"),
+        },
+        ..ctx
+    })
+}
+
+pub fn recipients_group_add(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::RecipientsGroupAdd {
+            name: required_arg(args, "name")?,
+            gpg_key_ids: optional_args(args, "gpg-key-id"),
+        },
+        ..ctx
+    })
+}
+
+pub fn recipients_group_remove(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::RecipientsGroupRemove {
+            name: required_arg(args, "name")?,
+            gpg_key_ids: optional_args(args, "gpg-key-id"),
+        },
         ..ctx
     })
 }
@@ -104,6 +206,7 @@ pub fn recipients_add(ctx: Context, args: &ArgMatches) -> Result<Context, Error>
             },
             partitions: optional_args(args, "partition"),
             signing_key_id: args.value_of("signing-key").map(ToOwned::to_owned),
+            comment: args.value_of("comment").map(ToOwned::to_owned),
             gpg_key_ids: args.values_of("gpg-key-id")
                 .expect("Clap to assure this is a required arg")
                 .map(Into::into)
@@ -117,14 +220,116 @@ pub fn resource_show(ctx: Context, args: &ArgMatches) -> Result<Context, Error>
     Ok(Context {
         command: Command::ResourceShow {
             spec: required_os_arg(args, "path")?,
+            pointers: optional_args(args, "pointer"),
+            fields: optional_args(args, "field"),
         },
         ..ctx
     })
 }
 
-pub fn resource_list(ctx: Context, _args: &ArgMatches) -> Result<Context, Error> {
+fn assignments(args: &ArgMatches, name: &'static str, kind: &str) -> Result<Vec<(String, String)>, Error> {
+    optional_args::<&str>(args, name)
+        .into_iter()
+        .map(|a| {
+            let mut tokens = a.splitn(2, '=');
+            match (tokens.next(), tokens.next()) {
+                (Some(key), Some(value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+                _ => Err(format_err!("'{}' must have the form '<{}>=<value>'", a, kind)),
+            }
+        })
+        .collect()
+}
+
+pub fn resource_set(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
     Ok(Context {
-        command: Command::List,
+        command: Command::ResourceSet {
+            spec: required_os_arg(args, "path")?,
+            assignments: assignments(args, "assignment", "pointer")?,
+        },
+        ..ctx
+    })
+}
+
+pub fn resource_otp(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::ResourceOtp {
+            spec: required_os_arg(args, "path")?,
+            field: args.value_of("field").map(ToOwned::to_owned),
+        },
+        ..ctx
+    })
+}
+
+pub fn stale(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::Stale {
+            max_age_days: match args.value_of("days") {
+                Some(days) => Some(days.parse()?),
+                None => None,
+            },
+        },
+        ..ctx
+    })
+}
+
+pub fn meta_get(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::MetaGet {
+            spec: required_os_arg(args, "path")?,
+            fields: optional_args(args, "field"),
+        },
+        ..ctx
+    })
+}
+
+pub fn meta_set(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::MetaSet {
+            spec: required_os_arg(args, "path")?,
+            assignments: assignments(args, "assignment", "name")?,
+        },
+        ..ctx
+    })
+}
+
+pub fn tag(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    let additions = args.values_of("tag").into_iter().flat_map(|v| v.map(TagChange::add));
+    let removals = args.values_of("remove").into_iter().flat_map(|v| v.map(TagChange::remove));
+    Ok(Context {
+        command: Command::Tag {
+            spec: required_os_arg(args, "path")?,
+            changes: additions.chain(removals).collect::<Result<_, _>>()?,
+        },
+        ..ctx
+    })
+}
+
+pub fn resource_history(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::ResourceHistory {
+            spec: required_os_arg(args, "path")?,
+        },
+        ..ctx
+    })
+}
+
+pub fn resource_restore(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::ResourceRestore {
+            spec: required_os_arg(args, "path")?,
+            version: required_arg(args, "version")?,
+        },
+        ..ctx
+    })
+}
+
+pub fn resource_list(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    Ok(Context {
+        command: Command::List {
+            filters: assignments(args, "where", "name")?,
+            tags: optional_args(args, "tag"),
+            show_metadata: args.is_present("meta"),
+        },
         ..ctx
     })
 }
@@ -132,14 +337,21 @@ pub fn resource_list(ctx: Context, _args: &ArgMatches) -> Result<Context, Error>
 pub fn resource_edit(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
     Ok(Context {
         command: Command::ResourceEdit {
-            spec: required_os_arg(args, "path")?,
-            editor: required_os_arg(args, "editor")?,
+            specs: match args.values_of_os("path") {
+                Some(v) => v.map(PathBuf::from).collect(),
+                None => return Err(err_msg("BUG: expected clap argument 'path' to be set")),
+            },
+            editor: Editor {
+                command: required_arg(args, "editor")?,
+                wait: args.is_present("wait") || Editor::from_env().wait,
+            },
             try_encrypt: !args.is_present("no-try-encrypt"),
             mode: if args.is_present("no-create") {
                 CreateMode::NoCreate
             } else {
                 CreateMode::Create
             },
+            template: args.value_of("template").map(ToOwned::to_owned),
         },
         ..ctx
     })
@@ -164,6 +376,51 @@ pub fn resource_add(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
                 Some(v) => v.map(|s| s.try_into()).collect::<Result<_, _>>()?,
                 None => Vec::new(),
             },
+            fields: assignments(args, "field", "name")?,
+            prompt: args.is_present("prompt"),
+            multiline: args.is_present("multiline"),
+        },
+        ..ctx
+    })
+}
+
+pub fn resource_generate(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
+    let exclude_ambiguous = args.is_present("no-ambiguous");
+    let generator = if let Some(words) = args.value_of("words") {
+        Generator::Passphrase {
+            words: words.parse()?,
+            separator: required_arg(args, "separator")?,
+        }
+    } else if let Some(bytes) = args.value_of("hex") {
+        Generator::Hex { bytes: bytes.parse()? }
+    } else if let Some(bytes) = args.value_of("base64") {
+        Generator::Base64 { bytes: bytes.parse()? }
+    } else if let Some(template) = args.value_of("template") {
+        Generator::Template {
+            template: template.to_owned(),
+            exclude_ambiguous,
+        }
+    } else {
+        Generator::Password {
+            length: required_arg(args, "length")?,
+            classes: match args.values_of("classes") {
+                Some(v) => v.map(|c| c.parse().map_err(|e: String| err_msg(e)))
+                    .collect::<Result<_, _>>()?,
+                None => vec![CharacterClass::Lower, CharacterClass::Upper, CharacterClass::Digit],
+            },
+            exclude_ambiguous,
+        }
+    };
+    Ok(Context {
+        command: Command::ResourceGenerate {
+            spec: required_os_arg(args, "path")?,
+            generator,
+            print: args.is_present("print"),
+            mode: if args.is_present("force") {
+                WriteMode::AllowOverwrite
+            } else {
+                WriteMode::RefuseOverwrite
+            },
         },
         ..ctx
     })
@@ -187,6 +444,7 @@ pub fn init_from(ctx: Context, args: &ArgMatches) -> Result<Context, Error> {
             recipients_file,
             auto_import,
             trust_model,
+            key_policy: args.value_of_os("key-policy").map(PathBuf::from),
             secrets,
             gpg_keys_dir: required_os_arg(args, "gpg-keys-dir")?,
             gpg_key_ids: optional_args(args, "gpg-key-id"),
@@ -213,14 +471,39 @@ pub fn execute(args: &ArgMatches) -> Result<(), Error> {
             ("remove", Some(args)) => recipients_remove(context, args)?,
             ("init", Some(args)) => recipients_init(context, args)?,
             ("list", Some(args)) => recipients_list(context, args)?,
+            ("check", Some(args)) => recipients_check(context, args)?,
+            ("refresh", Some(args)) => recipients_refresh(context, args)?,
+            ("replace", Some(args)) => recipients_replace(context, args)?,
+            ("sign", Some(args)) => recipients_sign(context, args)?,
+            ("certify", Some(args)) => recipients_certify(context, args)?,
+            ("request", Some(args)) => recipients_request(context, args)?,
+            ("pending", Some(args)) => recipients_pending(context, args)?,
+            ("approve", Some(args)) => recipients_approve(context, args)?,
+            ("group", Some(args)) => match args.subcommand() {
+                ("add", Some(args)) => recipients_group_add(context, args)?,
+                ("remove", Some(args)) => recipients_group_remove(context, args)?,
+                _ => usage_and_exit(&args),
+            },
             _ => recipients_list(context, args)?,
         },
         ("init", Some(args)) => init_from(context, args)?,
         ("add", Some(args)) => resource_add(context, args)?,
+        ("generate", Some(args)) => resource_generate(context, args)?,
         ("remove", Some(args)) => vault_resource_remove(context, args)?,
         ("show", Some(args)) => resource_show(context, args)?,
+        ("set", Some(args)) => resource_set(context, args)?,
+        ("otp", Some(args)) => resource_otp(context, args)?,
+        ("meta", Some(args)) => match args.subcommand() {
+            ("get", Some(args)) => meta_get(context, args)?,
+            ("set", Some(args)) => meta_set(context, args)?,
+            _ => usage_and_exit(&args),
+        },
+        ("tag", Some(args)) => tag(context, args)?,
+        ("history", Some(args)) => resource_history(context, args)?,
+        ("restore", Some(args)) => resource_restore(context, args)?,
         ("edit", Some(args)) => resource_edit(context, args)?,
         ("list", Some(args)) => resource_list(context, args)?,
+        ("stale", Some(args)) => stale(context, args)?,
         _ => context,
     };
     let sout = stdout();
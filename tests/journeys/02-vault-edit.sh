@@ -173,6 +173,28 @@ EDITOR
         expect_run $WITH_FAILURE test -f "$(cat /tmp/filepath-with-decrypted-content)"
       }
     )
+    (when "editing several resources at once, naming one of them twice"
+      many_editor="$PWD/my-many-editor.sh"
+      (
+        cat <<'EDITOR' > "$many_editor"
+#!/bin/bash -e
+for file_to_edit in "$@"; do
+  echo "edited along with $#" > "$file_to_edit"
+done
+EDITOR
+        chmod +x "$many_editor"
+      )
+      it "succeeds" && {
+        EDITOR="$many_editor" \
+        expect_run $SUCCESSFULLY "$exe" edit new-edited-file from-stdin from-stdin.gpg
+      }
+      it "opens each resource only once" && {
+        expect_run_sh $SUCCESSFULLY "test \"\$('$exe' show from-stdin)\" = 'edited along with 2'"
+      }
+      it "changes all of them" && {
+        expect_run_sh $SUCCESSFULLY "test \"\$('$exe' show new-edited-file)\" = 'edited along with 2'"
+      }
+    )
     (when "editing an unknown resource with --no-create set"
       it "fails" && {
         WITH_SNAPSHOT="$snapshot/unknown-resource-edit" \
@@ -153,3 +153,25 @@ snapshot="$fixture/snapshots/vault/recipients/unverified"
     )
   )
 )
+
+(sandboxed
+  title "'vault recipient add unverified' with a key policy"
+  (with "a vault with a key policy and the key file of a requester who violates it"
+    { import_user "$fixture/tester.sec.asc"
+      "$exe" init --trust-model=web-of-trust --gpg-keys-dir ./keys --no-auto-import
+      echo -n secret | "$exe" add :secret
+      echo 'key_policy: {min_rsa_bits: 4096}' >> sy-vault.yml
+      cp "$fixture/b.pub.asc" ./keys/7435ACDC03D55429C41637C4DB9831D842C18D28
+    } &>/dev/null
+
+    (when "adding the requester"
+      it "fails" && {
+        expect_run $WITH_FAILURE "$exe" recipients add 42C18D28
+      }
+
+      it "does not import or certify the key of the requester" && {
+        expect_run $WITH_FAILURE gpg --list-keys 7435ACDC03D55429C41637C4DB9831D842C18D28
+      }
+    )
+  )
+)
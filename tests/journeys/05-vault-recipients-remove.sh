@@ -86,3 +86,31 @@ snapshot="$fixture/snapshots/vault/recipients/remove"
     )
   )
 )
+
+(sandboxed
+  title "vault recipient remove - hand-written entries"
+  (with "a vault whose recipients file lists a recipient by a lowercase short fingerprint"
+    { import_user "$fixture/tester.sec.asc"
+      "$exe" init --trust-model=web-of-trust --no-auto-import --gpg-keys-dir ./keys
+      gpg --import "$fixture/b.pub.asc"
+      trust_key 7435ACDC03D55429C41637C4DB9831D842C18D28
+      echo db9831d842c18d28 >> .gpg-id
+      echo a | "$exe" add :a
+    } &>/dev/null
+
+    (when "removing that recipient"
+      it "succeeds" && {
+        expect_run $SUCCESSFULLY "$exe" recipient remove b@example.com
+      }
+
+      it "removes the entry from the recipients file" && {
+        expect_run $WITH_FAILURE grep -i db9831d842c18d28 .gpg-id
+      }
+
+      it "prevents the removed recipient from seeing the secret" && (
+        as_user "$fixture/b.sec.asc"
+        expect_run $WITH_FAILURE "$exe" show a
+      )
+    )
+  )
+)
@@ -0,0 +1,88 @@
+#!/bin/bash
+
+set -eu
+exe=${1:?First argument is the executable under test}
+
+root="$(cd "${0%/*}" && pwd)"
+exe="$root/../../$exe"
+# shellcheck source=./tests/gpg-helpers.sh
+source "$root/../gpg-helpers.sh"
+
+WITH_FAILURE=1
+SUCCESSFULLY=0
+
+fixture="$root/fixtures"
+
+(sandboxed
+  title "vault recipients replace"
+  (with "a vault with a secret and two recipients"
+    { import_user "$fixture/tester.sec.asc"
+      "$exe" init --trust-model=web-of-trust --no-auto-import --gpg-keys-dir ./keys
+      echo a | "$exe" add :a
+      gpg --import "$fixture/b.pub.asc" "$fixture/c.pub.asc"
+      trust_key 7435ACDC03D55429C41637C4DB9831D842C18D28
+      trust_key 905E53FE2FC0A500100AB80B056F92A52DF04D4E
+      "$exe" recipients add --comment "b from ops" 42C18D28
+    } &>/dev/null
+
+    (when "the recipient to replace is not in the vault"
+      cp .gpg-id recipients-before
+      it "fails" && {
+        expect_run $WITH_FAILURE "$exe" recipients replace c@example.com b@example.com
+      }
+
+      it "does not alter the recipients" && {
+        expect_run $SUCCESSFULLY diff recipients-before .gpg-id
+      }
+    )
+
+    (when "the old and the new recipient are the same"
+      it "fails" && {
+        expect_run $WITH_FAILURE "$exe" recipients replace b@example.com b@example.com
+      }
+    )
+
+    (when "the new recipient violates the key policy of the vault"
+      cp .gpg-id recipients-before
+      cp sy-vault.yml vault-before
+      echo 'key_policy: {min_rsa_bits: 4096}' >> sy-vault.yml
+      it "fails" && {
+        expect_run $WITH_FAILURE "$exe" recipients replace b@example.com c@example.com
+      }
+
+      it "does not alter the recipients" && {
+        expect_run $SUCCESSFULLY diff recipients-before .gpg-id
+      }
+      mv vault-before sy-vault.yml
+    )
+
+    (when "replacing a recipient by one who isn't a recipient yet"
+      it "succeeds" && {
+        expect_run $SUCCESSFULLY "$exe" recipients replace b@example.com c@example.com
+      }
+
+      it "replaces the fingerprint in the recipients file" && {
+        expect_run $WITH_FAILURE grep 7435ACDC03D55429C41637C4DB9831D842C18D28 .gpg-id
+        expect_run $SUCCESSFULLY grep 905E53FE2FC0A500100AB80B056F92A52DF04D4E .gpg-id
+      }
+
+      it "keeps the comment of the replaced recipient" && {
+        expect_run $SUCCESSFULLY grep '905E53FE2FC0A500100AB80B056F92A52DF04D4E # b from ops' .gpg-id
+      }
+
+      it "exports the key of the new recipient" && {
+        expect_exists ./keys/905E53FE2FC0A500100AB80B056F92A52DF04D4E
+      }
+
+      it "allows the new recipient to see the secret" && (
+        as_user "$fixture/c.sec.asc"
+        expect_run $SUCCESSFULLY "$exe" show a
+      )
+
+      it "prevents the old recipient from seeing the secret" && (
+        as_user "$fixture/b.sec.asc"
+        expect_run $WITH_FAILURE "$exe" show a
+      )
+    )
+  )
+)
@@ -0,0 +1,106 @@
+#!/bin/bash
+
+set -eu
+exe=${1:?First argument is the executable under test}
+
+root="$(cd "${0%/*}" && pwd)"
+exe="$root/../../$exe"
+# shellcheck source=./tests/gpg-helpers.sh
+source "$root/../gpg-helpers.sh"
+
+WITH_FAILURE=1
+SUCCESSFULLY=0
+
+fixture="$root/fixtures"
+
+(sandboxed
+  title "vault recipients group"
+  (with "a vault with a secret and a single recipient"
+    { import_user "$fixture/tester.sec.asc"
+      "$exe" init --trust-model=web-of-trust --no-auto-import --gpg-keys-dir ./keys
+      echo a | "$exe" add :a
+      gpg --import "$fixture/b.pub.asc" "$fixture/c.pub.asc"
+      trust_key 7435ACDC03D55429C41637C4DB9831D842C18D28
+      trust_key 905E53FE2FC0A500100AB80B056F92A52DF04D4E
+    } &>/dev/null
+
+    (when "removing members from a group which does not exist"
+      it "fails" && {
+        expect_run $WITH_FAILURE "$exe" recipients group remove ops 42C18D28
+      }
+    )
+
+    (when "adding a member to a new group"
+      it "succeeds" && {
+        expect_run $SUCCESSFULLY "$exe" recipients group add ops b@example.com
+      }
+
+      it "stores the group in the vault description file" && {
+        expect_run $SUCCESSFULLY grep 7435ACDC03D55429C41637C4DB9831D842C18D28 sy-vault.yml
+      }
+
+      it "exports the key of the member" && {
+        expect_exists ./keys/7435ACDC03D55429C41637C4DB9831D842C18D28
+      }
+
+      (when "the group is referenced by the recipients file and another member is added"
+        echo '@ops' >> .gpg-id
+        (when "the new member violates the key policy of the vault"
+          cp sy-vault.yml vault-before
+          echo 'key_policy: {min_rsa_bits: 4096}' >> sy-vault.yml
+          it "fails" && {
+            expect_run $WITH_FAILURE "$exe" recipients group add ops c@example.com
+          }
+
+          it "does not add the member to the group" && {
+            expect_run $WITH_FAILURE grep 905E53FE2FC0A500100AB80B056F92A52DF04D4E sy-vault.yml
+          }
+          mv vault-before sy-vault.yml
+        )
+
+        it "succeeds" && {
+          expect_run $SUCCESSFULLY "$exe" recipients group add ops c@example.com
+        }
+
+        it "re-encrypts the secret for all members of the group" && (
+          as_user "$fixture/b.sec.asc"
+          expect_run $SUCCESSFULLY "$exe" show a
+          as_user "$fixture/c.sec.asc"
+          expect_run $SUCCESSFULLY "$exe" show a
+        )
+
+        (when "removing a recipient which is not a member"
+          it "fails" && {
+            expect_run $WITH_FAILURE "$exe" recipients group remove ops 50F48332
+          }
+        )
+
+        (when "removing a member of the group"
+          it "succeeds" && {
+            expect_run $SUCCESSFULLY "$exe" recipients group remove ops 2DF04D4E
+          }
+
+          it "prevents the removed member from seeing the secret" && (
+            as_user "$fixture/c.sec.asc"
+            expect_run $WITH_FAILURE "$exe" show a
+          )
+
+          it "removes the key of the member, as it is not used anymore" && {
+            expect_run $WITH_FAILURE test -f ./keys/905E53FE2FC0A500100AB80B056F92A52DF04D4E
+          }
+
+          (when "removing the last member of the group"
+            it "fails" && {
+              expect_run $WITH_FAILURE "$exe" recipients group remove ops 42C18D28
+            }
+
+            it "keeps the secret readable for the member" && (
+              as_user "$fixture/b.sec.asc"
+              expect_run $SUCCESSFULLY "$exe" show a
+            )
+          )
+        )
+      )
+    )
+  )
+)
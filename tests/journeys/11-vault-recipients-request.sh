@@ -0,0 +1,106 @@
+#!/bin/bash
+
+set -eu
+exe=${1:?First argument is the executable under test}
+
+root="$(cd "${0%/*}" && pwd)"
+exe="$root/../../$exe"
+# shellcheck source=./tests/gpg-helpers.sh
+source "$root/../gpg-helpers.sh"
+
+WITH_FAILURE=1
+SUCCESSFULLY=0
+
+fixture="$root/fixtures"
+C_FPR=905E53FE2FC0A500100AB80B056F92A52DF04D4E
+B_FPR=7435ACDC03D55429C41637C4DB9831D842C18D28
+
+(sandboxed
+  title "vault recipients request, pending and approve"
+  (with "a vault with a secret and a single recipient"
+    { import_user "$fixture/tester.sec.asc"
+      "$exe" init --trust-model=web-of-trust --no-auto-import --gpg-keys-dir ./keys
+      echo a | "$exe" add :a
+    } &>/dev/null
+
+    (when "there are no requests"
+      it "approving fails" && {
+        expect_run $WITH_FAILURE "$exe" recipients approve $C_FPR
+      }
+    )
+
+    (when "an unknown user requests access with a note"
+      it "succeeds" && (
+        as_user "$fixture/c.sec.asc"
+        expect_run $SUCCESSFULLY "$exe" recipients request --note "c from ops"
+      )
+
+      it "exports the key of the user and the signed note into the pending directory" && {
+        expect_exists ./keys/pending/$C_FPR
+        expect_exists ./keys/pending/$C_FPR.note.asc
+      }
+
+      it "lists the request along with its note" && {
+        expect_run_sh $SUCCESSFULLY "'$exe' recipients pending | grep 'c from ops'"
+      }
+
+      (when "the key file of a request contains another key"
+        cat "$fixture/b.pub.asc" ./keys/pending/$C_FPR > ./keys/pending/$B_FPR
+        it "approving it fails" && {
+          expect_run $WITH_FAILURE "$exe" recipients approve $B_FPR
+        }
+
+        it "does not add it as recipient" && {
+          expect_run $WITH_FAILURE grep $B_FPR .gpg-id
+        }
+        rm ./keys/pending/$B_FPR
+      )
+
+      (when "the request is approved"
+        it "succeeds" && {
+          expect_run $SUCCESSFULLY "$exe" recipients approve 2DF04D4E
+        }
+
+        it "adds the requester as recipient, using the note as comment" && {
+          expect_run $SUCCESSFULLY grep $C_FPR .gpg-id
+          expect_run $SUCCESSFULLY grep 'c from ops' .gpg-id
+        }
+
+        it "removes the request" && {
+          expect_run $WITH_FAILURE test -e ./keys/pending/$C_FPR
+          expect_run $WITH_FAILURE test -e ./keys/pending/$C_FPR.note.asc
+        }
+
+        it "exports the certified key of the requester" && {
+          expect_exists ./keys/$C_FPR
+        }
+
+        it "allows the requester to see the secret" && (
+          as_user "$fixture/c.sec.asc"
+          expect_run $SUCCESSFULLY "$exe" show a
+        )
+      )
+    )
+
+    (when "a request comes with a note which is not signed by the requester"
+      {
+        gpg --import "$fixture/b.pub.asc"
+        mkdir -p ./keys/pending
+        gpg --export --armor $B_FPR > ./keys/pending/$B_FPR
+        echo "forged" | gpg --clearsign --batch > ./keys/pending/$B_FPR.note.asc
+      } &>/dev/null
+
+      it "lists the request with a warning about the note" && {
+        expect_run_sh $SUCCESSFULLY "'$exe' recipients pending | grep 'not signed by the requesting key'"
+      }
+
+      it "approving succeeds" && {
+        expect_run $SUCCESSFULLY "$exe" recipients approve $B_FPR
+      }
+
+      it "does not use the note as comment" && {
+        expect_run $WITH_FAILURE grep forged .gpg-id
+      }
+    )
+  )
+)
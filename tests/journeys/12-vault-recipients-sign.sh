@@ -0,0 +1,93 @@
+#!/bin/bash
+
+set -eu
+exe=${1:?First argument is the executable under test}
+
+root="$(cd "${0%/*}" && pwd)"
+exe="$root/../../$exe"
+# shellcheck source=./tests/gpg-helpers.sh
+source "$root/../gpg-helpers.sh"
+
+WITH_FAILURE=1
+SUCCESSFULLY=0
+
+fixture="$root/fixtures"
+B_FPR=7435ACDC03D55429C41637C4DB9831D842C18D28
+C_FPR=905E53FE2FC0A500100AB80B056F92A52DF04D4E
+
+(sandboxed
+  title "vault recipients sign"
+  (with "a vault with a secret and two recipients"
+    { import_user "$fixture/tester.sec.asc"
+      "$exe" init --trust-model=web-of-trust --no-auto-import --gpg-keys-dir ./keys
+      echo a | "$exe" add :a
+      gpg --import "$fixture/b.pub.asc"
+      trust_key $B_FPR
+      "$exe" recipients add 42C18D28
+    } &>/dev/null
+
+    (when "signing the recipients"
+      it "succeeds" && {
+        expect_run $SUCCESSFULLY "$exe" recipients sign
+      }
+
+      it "writes the signature next to the recipients file" && {
+        expect_exists .gpg-id.sig
+      }
+
+      it "notes in the vault description file that the recipients must be signed" && {
+        expect_run $SUCCESSFULLY grep 'signed_recipients: true' sy-vault.yml
+      }
+
+      it "allows to add secrets" && {
+        expect_run_sh $SUCCESSFULLY "echo b | '$exe' add :b"
+      }
+
+      (when "a recipient is added without signing the recipients again"
+        cp .gpg-id recipients-before
+        echo $C_FPR >> .gpg-id
+        it "refuses to encrypt" && {
+          expect_run_sh $WITH_FAILURE "echo c | '$exe' add :c"
+        }
+        mv recipients-before .gpg-id
+      )
+
+      (when "the signature is deleted"
+        mv .gpg-id.sig signature-before
+        it "refuses to encrypt" && {
+          expect_run_sh $WITH_FAILURE "echo c | '$exe' add :c"
+        }
+        mv signature-before .gpg-id.sig
+      )
+
+      (when "someone who is not a recipient adds themselves and signs the recipients"
+        { gpg --import "$fixture/c.pub.asc"
+          trust_key $C_FPR
+        } &>/dev/null
+        cp .gpg-id recipients-before
+        cp .gpg-id.sig signature-before
+        echo $C_FPR >> .gpg-id
+        it "signing succeeds for them" && (
+          as_user "$fixture/c.sec.asc"
+          expect_run $SUCCESSFULLY "$exe" recipients sign
+        )
+
+        it "refuses to encrypt, as they are not among the recipients accepted last" && {
+          expect_run_sh $WITH_FAILURE "echo c | '$exe' add :c"
+        }
+        mv recipients-before .gpg-id
+        mv signature-before .gpg-id.sig
+      )
+
+      (when "a recipient is removed"
+        it "succeeds" && {
+          expect_run $SUCCESSFULLY "$exe" recipients remove 42C18D28
+        }
+
+        it "signs the changed recipients again" && {
+          expect_run_sh $SUCCESSFULLY "echo d | '$exe' add :d"
+        }
+      )
+    )
+  )
+)
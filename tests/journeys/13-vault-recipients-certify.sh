@@ -0,0 +1,87 @@
+#!/bin/bash
+
+set -eu
+exe=${1:?First argument is the executable under test}
+
+root="$(cd "${0%/*}" && pwd)"
+exe="$root/../../$exe"
+# shellcheck source=./tests/gpg-helpers.sh
+source "$root/../gpg-helpers.sh"
+
+WITH_FAILURE=1
+SUCCESSFULLY=0
+
+fixture="$root/fixtures"
+A_FPR=1E1114E11B362B46B53E1ABAEF17047AB488BD82
+C_FPR=905E53FE2FC0A500100AB80B056F92A52DF04D4E
+
+(sandboxed
+  title "vault recipients certify"
+  (with "a vault using the 'vault-members' trust model with a secret and two recipients"
+    { import_user "$fixture/tester.sec.asc"
+      "$exe" init --trust-model=vault-members --no-auto-import --gpg-keys-dir ./keys
+      echo a | "$exe" add :a
+      gpg --import "$fixture/b.pub.asc" "$fixture/c.pub.asc"
+      "$exe" recipients add 42C18D28
+    } &>/dev/null
+
+    (when "certifying your own key"
+      it "fails" && {
+        expect_run $WITH_FAILURE "$exe" recipients certify 50F48332
+      }
+    )
+
+    (when "two keys which only certify each other are added to the recipients by hand"
+      { (as_user "$fixture/a.sec.asc"
+          gpg --import "$fixture/c.pub.asc"
+          gpg --batch --yes --quick-sign-key $C_FPR
+          gpg --export --armor $C_FPR > ./keys/$C_FPR
+          gpg --export --armor $A_FPR > ./keys/$A_FPR
+        )
+        (as_user "$fixture/c.sec.asc"
+          gpg --import ./keys/$A_FPR
+          gpg --batch --yes --quick-sign-key $A_FPR
+          gpg --export --armor $A_FPR > ./keys/$A_FPR
+        )
+        gpg --import ./keys/$A_FPR ./keys/$C_FPR
+      } &>/dev/null
+      cp .gpg-id recipients-before
+      echo $A_FPR >> .gpg-id
+      echo $C_FPR >> .gpg-id
+
+      it "refuses to encrypt, as none of their certifiers is trusted" && {
+        expect_run_sh $WITH_FAILURE "echo b | '$exe' add :b"
+      }
+      mv recipients-before .gpg-id
+      rm ./keys/$A_FPR ./keys/$C_FPR
+    )
+
+    (when "a key is added to the recipients by hand"
+      cp .gpg-id recipients-before
+      echo $C_FPR >> .gpg-id
+
+      it "refuses to encrypt as long as it is not certified" && {
+        expect_run_sh $WITH_FAILURE "echo b | '$exe' add :b"
+      }
+
+      (when "certifying it"
+        it "succeeds" && {
+          expect_run $SUCCESSFULLY "$exe" recipients certify 2DF04D4E
+        }
+
+        it "exports the key along with the certification" && {
+          expect_exists ./keys/$C_FPR
+        }
+
+        it "allows to encrypt" && {
+          expect_run_sh $SUCCESSFULLY "echo b | '$exe' add :b"
+        }
+
+        it "allows the certified recipient to see the secret" && (
+          as_user "$fixture/c.sec.asc"
+          expect_run $SUCCESSFULLY "$exe" show b
+        )
+      )
+    )
+  )
+)
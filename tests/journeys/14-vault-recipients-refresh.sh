@@ -0,0 +1,74 @@
+#!/bin/bash
+
+set -eu
+exe=${1:?First argument is the executable under test}
+
+root="$(cd "${0%/*}" && pwd)"
+exe="$root/../../$exe"
+# shellcheck source=./tests/gpg-helpers.sh
+source "$root/../gpg-helpers.sh"
+
+WITH_FAILURE=1
+SUCCESSFULLY=0
+
+fixture="$root/fixtures"
+TESTER_FPR=D6339718E9B58FCE3C66C78AAA5B7BF150F48332
+B_FPR=7435ACDC03D55429C41637C4DB9831D842C18D28
+C_FPR=905E53FE2FC0A500100AB80B056F92A52DF04D4E
+
+(sandboxed
+  title "vault recipients refresh"
+  (with "a vault with two recipients whose keys are exported"
+    { import_user "$fixture/tester.sec.asc"
+      "$exe" init --trust-model=web-of-trust --no-auto-import --gpg-keys-dir ./keys
+      echo a | "$exe" add :a
+      gpg --import "$fixture/b.pub.asc"
+      trust_key $B_FPR
+      "$exe" recipients add 42C18D28
+    } &>/dev/null
+
+    (when "no key changed"
+      cp ./keys/$TESTER_FPR tester-key-before
+      it "succeeds and reports all keys as up to date" && {
+        expect_run_sh $SUCCESSFULLY "'$exe' recipients refresh | grep '2 public key(s) were up to date'"
+      }
+
+      it "does not alter the exported keys" && {
+        expect_run $SUCCESSFULLY diff tester-key-before ./keys/$TESTER_FPR
+      }
+    )
+
+    (when "the expiry of a key was changed in the keyring"
+      gpg --batch --quick-set-expire $TESTER_FPR 2y &>/dev/null
+      it "succeeds and reports the updated key" && {
+        expect_run_sh $SUCCESSFULLY "'$exe' recipients refresh | grep 'Updated public key'"
+      }
+
+      it "exports the updated key" && {
+        expect_run $WITH_FAILURE diff tester-key-before ./keys/$TESTER_FPR
+      }
+
+      it "keeps the unchanged key" && {
+        expect_run_sh $SUCCESSFULLY "'$exe' recipients refresh | grep '2 public key(s) were up to date'"
+      }
+    )
+
+    (when "importing keys from a file which contains the key of a non-recipient"
+      cat ./keys/$B_FPR "$fixture/c.pub.asc" > updates.asc
+      it "fails" && {
+        expect_run $WITH_FAILURE "$exe" recipients refresh --from updates.asc
+      }
+
+      it "does not import any of its keys" && {
+        expect_run $WITH_FAILURE gpg --list-keys $C_FPR
+      }
+    )
+
+    (when "importing keys from a file which contains only keys of recipients"
+      cp ./keys/$B_FPR updates.asc
+      it "succeeds" && {
+        expect_run $SUCCESSFULLY "$exe" recipients refresh --from updates.asc
+      }
+    )
+  )
+)
@@ -0,0 +1,62 @@
+#!/bin/bash
+
+set -eu
+exe=${1:?First argument is the executable under test}
+
+root="$(cd "${0%/*}" && pwd)"
+exe="$root/../../$exe"
+# shellcheck source=./tests/gpg-helpers.sh
+source "$root/../gpg-helpers.sh"
+
+WITH_FAILURE=1
+SUCCESSFULLY=0
+
+fixture="$root/fixtures"
+
+(sandboxed
+  title "changes which leave the content as it was"
+  (with "a vault keeping previous versions and a structured resource"
+    { import_user "$fixture/tester.sec.asc"
+      "$exe" init --trust-model=web-of-trust --no-auto-import
+      echo "keep_versions: 3" >> sy-vault.yml
+      echo "user: admin" | "$exe" add :db.yml
+    } &>/dev/null
+    encrypted_before="$(md5sum db.yml.gpg)"
+
+    (when "editing the resource without changing it"
+      it "succeeds and says so" && {
+        expect_run_sh $SUCCESSFULLY "'$exe' edit --editor true db.yml | grep -q \"No changes to 'db.yml'.\""
+      }
+      it "does not re-encrypt the resource" && {
+        expect_equals "$encrypted_before" "$(md5sum db.yml.gpg)"
+      }
+      it "does not keep a version" && {
+        expect_run $WITH_FAILURE "$exe" history db.yml
+      }
+    )
+
+    (when "setting a value to what it already is"
+      it "succeeds and says so" && {
+        expect_run_sh $SUCCESSFULLY "'$exe' set db.yml user=admin | grep -q \"No changes to 'db.yml'.\""
+      }
+      it "does not re-encrypt the resource" && {
+        expect_equals "$encrypted_before" "$(md5sum db.yml.gpg)"
+      }
+      it "does not keep a version" && {
+        expect_run $WITH_FAILURE "$exe" history db.yml
+      }
+    )
+
+    (when "setting a value to something else"
+      it "succeeds" && {
+        expect_run $SUCCESSFULLY "$exe" set db.yml user=root
+      }
+      it "re-encrypts the resource" && {
+        expect_run_sh $WITH_FAILURE "test \"$encrypted_before\" = \"\$(md5sum db.yml.gpg)\""
+      }
+      it "keeps the previous version" && {
+        expect_run_sh $SUCCESSFULLY "test \$('$exe' history db.yml | wc -l) -eq 1"
+      }
+    )
+  )
+)